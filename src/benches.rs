@@ -0,0 +1,78 @@
+use rand::prelude::*;
+use test::Bencher;
+
+use crate::{Maze, Placement};
+use crate::generator::{GeneratorKind, SelectionPolicy};
+use crate::solver::SolverKind;
+
+fn bench_generate(b: &mut Bencher, kind: GeneratorKind, dimensions: &[usize]) {
+    let mut rng = StdRng::seed_from_u64(0x5b7e13c94a0f26d8);
+    let mut maze = Maze::new(dimensions.to_vec());
+    let generator = kind.generator(SelectionPolicy::default(), 1);
+    b.iter(|| maze.generate(generator.as_ref(), &mut rng));
+}
+
+#[bench]
+fn bench_prim(b: &mut Bencher) {
+    bench_generate(b, GeneratorKind::Prim, &[16, 16, 16]);
+}
+
+#[bench]
+fn bench_recursive_backtracker(b: &mut Bencher) {
+    bench_generate(b, GeneratorKind::RecursiveBacktracker, &[16, 16, 16]);
+}
+
+#[bench]
+fn bench_kruskal(b: &mut Bencher) {
+    bench_generate(b, GeneratorKind::Kruskal, &[16, 16, 16]);
+}
+
+#[bench]
+fn bench_wilson(b: &mut Bencher) {
+    bench_generate(b, GeneratorKind::Wilson, &[16, 16, 16]);
+}
+
+#[bench]
+fn bench_eller(b: &mut Bencher) {
+    bench_generate(b, GeneratorKind::Eller, &[16, 16, 16]);
+}
+
+#[bench]
+fn bench_growing_tree(b: &mut Bencher) {
+    bench_generate(b, GeneratorKind::GrowingTree, &[16, 16, 16]);
+}
+
+#[bench]
+fn bench_recursive_division(b: &mut Bencher) {
+    bench_generate(b, GeneratorKind::RecursiveDivision, &[16, 16, 16]);
+}
+
+fn bench_solve(b: &mut Bencher, kind: SolverKind) {
+    let mut rng = StdRng::seed_from_u64(0x0d4a9e62f7b3c815);
+    let mut maze = Maze::new(vec![64, 64]);
+    maze.set_placement(Placement::Diameter);
+    maze.generate(&crate::generator::Kruskal, &mut rng);
+    maze.braid(0.5, &mut rng);
+    let solver = kind.solver();
+    b.iter(|| maze.solve_with(solver.as_ref()));
+}
+
+#[bench]
+fn bench_breadth_first(b: &mut Bencher) {
+    bench_solve(b, SolverKind::BreadthFirst);
+}
+
+#[bench]
+fn bench_dijkstra(b: &mut Bencher) {
+    bench_solve(b, SolverKind::Dijkstra);
+}
+
+#[bench]
+fn bench_a_star(b: &mut Bencher) {
+    bench_solve(b, SolverKind::AStar);
+}
+
+#[bench]
+fn bench_bidirectional(b: &mut Bencher) {
+    bench_solve(b, SolverKind::Bidirectional);
+}
@@ -114,6 +114,11 @@ impl<T: BinaryHashHeapItem> BinaryHashHeap<T> {
         true
     }
 
+    /// Iterate over the items in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+
     /// Pop an item from the heap.
     pub fn pop(&mut self) -> Option<T> {
         if self.items.is_empty() {
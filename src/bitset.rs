@@ -0,0 +1,117 @@
+/// Fixed size array of bits packed into words, taking an eighth of the memory of a [Vec] of
+/// [bool].
+///
+/// Bits past the end of the last word are always kept clear, so that comparing and hashing work on
+/// the words directly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BitSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitSet {
+    const WORD_BITS: usize = u64::BITS as usize;
+
+    /// Create len bits all set to value.
+    pub fn new(len: usize, value: bool) -> Self {
+        let mut result = Self {
+            words: vec![0; len.div_ceil(Self::WORD_BITS)],
+            len,
+        };
+        result.fill(value);
+        result
+    }
+
+    /// Number of bytes needed to store len bits.
+    pub fn memory(len: usize) -> usize {
+        len.div_ceil(Self::WORD_BITS).saturating_mul(size_of::<u64>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len);
+        self.words[index / Self::WORD_BITS] >> (index % Self::WORD_BITS) & 1 != 0
+    }
+
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len);
+        let mask = 1 << (index % Self::WORD_BITS);
+        if value {
+            self.words[index / Self::WORD_BITS] |= mask;
+        } else {
+            self.words[index / Self::WORD_BITS] &= !mask;
+        }
+    }
+
+    /// Set every bit to value.
+    pub fn fill(&mut self, value: bool) {
+        self.words.fill(if value { u64::MAX } else { 0 });
+        if value && !self.len.is_multiple_of(Self::WORD_BITS) {
+            *self.words.last_mut().unwrap() = (1 << (self.len % Self::WORD_BITS)) - 1;
+        }
+    }
+
+    /// Count the bits that are set.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|index| self.get(index))
+    }
+}
+
+impl FromIterator<bool> for BitSet {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut result = Self { words: Vec::new(), len: 0 };
+        for value in iter {
+            if result.len.is_multiple_of(Self::WORD_BITS) {
+                result.words.push(0);
+            }
+            result.len += 1;
+            result.set(result.len - 1, value);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test() {
+        let mut rng = StdRng::seed_from_u64(0x3c81f5a0e9d24b67);
+
+        for len in [0, 1, 63, 64, 65, 200] {
+            for value in [false, true] {
+                // Compare against a plain vector of booleans.
+                let mut bits = BitSet::new(len, value);
+                let mut bools = vec![value; len];
+                for _ in 0..len * 4 {
+                    let index = rng.random_range(0..len);
+                    let value = rng.random();
+                    bits.set(index, value);
+                    bools[index] = value;
+                }
+
+                assert_eq!(bits.len(), len);
+                assert!(bits.iter().eq(bools.iter().copied()));
+                assert_eq!(bools.iter().copied().collect::<BitSet>(), bits);
+                assert_eq!(bits.count_ones(), bools.iter().filter(|value| **value).count());
+
+                bits.fill(true);
+                assert_eq!(bits.count_ones(), len);
+                assert_eq!(bits, BitSet::new(len, true));
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+/// Union-find over the integers `0..len`, backed by a flat parent array.
+///
+/// Uses union by size and path halving, so both [DisjointSet::find] and [DisjointSet::union] are
+/// effectively constant time.
+#[derive(Debug, Clone)]
+pub struct DisjointSet {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl DisjointSet {
+    /// Create `len` singleton sets.
+    pub fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+        }
+    }
+
+    /// Number of bytes needed for `len` elements.
+    pub fn memory(len: usize) -> usize {
+        len.saturating_mul(size_of::<usize>() * 2)
+    }
+
+    /// Find the representative of the set containing element.
+    pub fn find(&mut self, mut element: usize) -> usize {
+        while self.parents[element] != element {
+            self.parents[element] = self.parents[self.parents[element]];
+            element = self.parents[element];
+        }
+        element
+    }
+
+    /// Merge the sets containing the two elements.
+    ///
+    /// Return false if they are already in the same set.
+    pub fn union(&mut self, element1: usize, element2: usize) -> bool {
+        let mut root1 = self.find(element1);
+        let mut root2 = self.find(element2);
+        if root1 == root2 {
+            return false;
+        }
+
+        if self.sizes[root1] < self.sizes[root2] {
+            std::mem::swap(&mut root1, &mut root2);
+        }
+
+        self.parents[root2] = root1;
+        self.sizes[root1] += self.sizes[root2];
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test() {
+        let mut rng = StdRng::seed_from_u64(0x51c0a3d2f6e8b417);
+
+        // Compare against a naive labelling where every element stores its set label directly.
+        let mut sets = DisjointSet::new(100);
+        let mut labels = (0..100).collect::<Vec<usize>>();
+        for _ in 0..1024 {
+            let element1 = rng.random_range(0..100);
+            let element2 = rng.random_range(0..100);
+
+            let label1 = labels[element1];
+            let label2 = labels[element2];
+            assert_eq!(sets.union(element1, element2), label1 != label2);
+            for label in labels.iter_mut() {
+                if *label == label2 {
+                    *label = label1;
+                }
+            }
+
+            for element1 in 0..100 {
+                let element2 = rng.random_range(0..100);
+                assert_eq!(sets.find(element1) == sets.find(element2), labels[element1] == labels[element2]);
+            }
+        }
+    }
+}
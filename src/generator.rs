@@ -0,0 +1,1189 @@
+use rand::prelude::*;
+use rand::distr::weighted::WeightedIndex;
+
+use std::collections::{hash_map, HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{saturating_cell_count, CellId, Maze, Wall};
+use crate::disjoint_set::DisjointSet;
+
+/// Algorithm used to carve passages into a [Maze].
+///
+/// [Maze::generate] resets every wall before starting the generator, so an implementation only
+/// needs to knock down walls until every cell is reachable, and picks the start and end
+/// afterwards. A generator can instead ask to start with every wall open with
+/// [MazeGenerator::starts_open], and put walls up until there are no loops left.
+///
+/// Cells excluded by the mask of the maze must never be visited. If the mask splits the maze into
+/// several pieces, it is fine to only carve out some of them, as [Maze::generate] joins up
+/// whatever is left over.
+pub trait MazeGenerator {
+    /// Set up the state needed to carve out the maze, which is then carved out one wall at a time
+    /// by [GeneratorStepper::step].
+    fn start(&self, maze: &Maze, rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper>;
+
+    /// Whether the generator starts from a maze with every wall open, as set up by
+    /// [Maze::open_walls], rather than one with every wall up.
+    fn starts_open(&self) -> bool {
+        false
+    }
+
+    /// Human readable description of the generator including its parameters, which is recorded
+    /// with the maze so that the layout can be reproduced.
+    fn describe(&self) -> String;
+
+    /// Estimate the number of bytes the generator needs on top of the maze itself for a maze with
+    /// the given dimensions, erring on the side of too much. This must not overflow, however large
+    /// the dimensions are.
+    fn estimate_memory(&self, dimensions: &[usize]) -> usize;
+}
+
+/// Generator part way through carving out a maze, so that generation can be paused and resumed,
+/// e.g. to show it as it happens.
+///
+/// The maze passed to every step must be the one the generator was started on, and must not be
+/// modified in between.
+pub trait GeneratorStepper {
+    /// Knock down the next wall, or put it up if the generator starts open, and return it, or
+    /// return [None] if the generator is done.
+    fn step(&mut self, maze: &mut Maze, rng: &mut dyn RngCore) -> Option<Wall>;
+}
+
+/// Randomized Prim's algorithm. Grows the maze from a random cell by repeatedly knocking down a
+/// random wall on its frontier, which gives short and bushy passages.
+///
+/// The frontier is kept separately for each axis, so that walls can be picked according to the
+/// axis weights of the maze.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Prim;
+
+impl MazeGenerator for Prim {
+    fn start(&self, maze: &Maze, rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper> {
+        let root = maze.random_cell(rng);
+
+        let mut visited = vec![false; maze.cell_count()];
+        visited[root.0] = true;
+
+        let mut walls = (0..maze.dimensions.len()).map(|_| Vec::new()).collect::<Vec<_>>();
+        for wall in maze.cell_walls(root) {
+            walls[wall.axis].push(wall);
+        }
+
+        Box::new(PrimStepper { visited, walls })
+    }
+
+    fn describe(&self) -> String {
+        String::from("Prim")
+    }
+
+    fn estimate_memory(&self, dimensions: &[usize]) -> usize {
+        // Every wall might end up on the frontier.
+        let cell_count = saturating_cell_count(dimensions);
+        cell_count.saturating_add(cell_count.saturating_mul(dimensions.len()).saturating_mul(size_of::<Wall>()))
+    }
+}
+
+struct PrimStepper {
+    visited: Vec<bool>,
+    /// Frontier of the maze, by axis.
+    walls: Vec<Vec<Wall>>,
+}
+
+impl GeneratorStepper for PrimStepper {
+    fn step(&mut self, maze: &mut Maze, rng: &mut dyn RngCore) -> Option<Wall> {
+        while let Ok(distribution) = WeightedIndex::new(self.walls.iter().enumerate().map(|(axis, walls)| walls.len() as f64 * maze.axis_weights[axis])) {
+            let axis_walls = &mut self.walls[distribution.sample(rng)];
+            let wall = axis_walls.swap_remove(rng.random_range(0..axis_walls.len()));
+            let Some(cells) = maze.wall_cells(&wall) else {
+                continue;
+            };
+
+            if cells.iter().any(|cell| maze.is_excluded(*cell)) {
+                continue;
+            }
+
+            let mut okay = false;
+            for cell in cells {
+                if !self.visited[cell.0] {
+                    for wall in maze.cell_walls(cell) {
+                        if maze.get_wall(&wall) {
+                            self.walls[wall.axis].push(wall);
+                        }
+                    }
+                    self.visited[cell.0] = true;
+                    okay = true;
+                }
+            }
+
+            if okay {
+                maze.set_wall(&wall, false);
+                return Some(wall);
+            }
+        }
+
+        None
+    }
+}
+
+/// Randomized depth-first search. Walks away from a random cell for as long as possible before
+/// backtracking, which gives long and winding corridors.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecursiveBacktracker;
+
+impl MazeGenerator for RecursiveBacktracker {
+    fn start(&self, maze: &Maze, rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper> {
+        let root = maze.random_cell(rng);
+
+        let mut visited = vec![false; maze.cell_count()];
+        visited[root.0] = true;
+
+        let stack = vec![root];
+
+        Box::new(RecursiveBacktrackerStepper { visited, stack })
+    }
+
+    fn describe(&self) -> String {
+        String::from("Recursive Backtracker")
+    }
+
+    fn estimate_memory(&self, dimensions: &[usize]) -> usize {
+        let cell_count = saturating_cell_count(dimensions);
+        cell_count.saturating_add(cell_count.saturating_mul(size_of::<CellId>()))
+    }
+}
+
+struct RecursiveBacktrackerStepper {
+    visited: Vec<bool>,
+    stack: Vec<CellId>,
+}
+
+impl GeneratorStepper for RecursiveBacktrackerStepper {
+    fn step(&mut self, maze: &mut Maze, rng: &mut dyn RngCore) -> Option<Wall> {
+        while let Some(&cell) = self.stack.last() {
+            let candidates = maze
+                .neighbours(cell)
+                .filter(|(_, neighbour_cell)| !self.visited[neighbour_cell.0])
+                .collect::<Vec<_>>();
+
+            let Some(index) = maze.choose_neighbour(&candidates, rng) else {
+                self.stack.pop();
+                continue;
+            };
+
+            let (wall, neighbour_cell) = candidates[index];
+            maze.set_wall(&wall, false);
+            self.visited[neighbour_cell.0] = true;
+            self.stack.push(neighbour_cell);
+            return Some(wall);
+        }
+
+        None
+    }
+}
+
+/// Randomized Kruskal's algorithm. Visits every wall in random order and knocks it down if it
+/// separates two cells that are not yet connected, which gives a more uniform texture.
+///
+/// If the maze is biased towards some axes, walls are instead visited in a weighted random order,
+/// by sorting them on exponentially distributed keys scaled by their axis weights.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Kruskal;
+
+impl MazeGenerator for Kruskal {
+    fn start(&self, maze: &Maze, rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper> {
+        let mut indices = (0..maze.walls.len()).collect::<Vec<_>>();
+        if maze.is_axis_biased() {
+            let stride = maze.cell_count();
+            let mut keys = indices
+                .into_iter()
+                .map(|index| (-(1.0 - rng.random::<f64>()).ln() / maze.axis_weights[index / stride], index))
+                .collect::<Vec<_>>();
+            keys.sort_by(|(key1, _), (key2, _)| key1.total_cmp(key2));
+            indices = keys.into_iter().map(|(_, index)| index).collect();
+        } else {
+            indices.shuffle(rng);
+        }
+
+        let sets = DisjointSet::new(maze.cell_count());
+        Box::new(KruskalStepper { indices: indices.into_iter(), sets })
+    }
+
+    fn describe(&self) -> String {
+        String::from("Kruskal")
+    }
+
+    fn estimate_memory(&self, dimensions: &[usize]) -> usize {
+        // Sorting on keys needs the indices and the keys at the same time.
+        let wall_count = saturating_cell_count(dimensions).saturating_mul(dimensions.len());
+        wall_count
+            .saturating_mul(size_of::<usize>() + size_of::<(f64, usize)>())
+            .saturating_add(DisjointSet::memory(saturating_cell_count(dimensions)))
+    }
+}
+
+struct KruskalStepper {
+    /// Indices of the walls yet to be visited.
+    indices: std::vec::IntoIter<usize>,
+    sets: DisjointSet,
+}
+
+impl GeneratorStepper for KruskalStepper {
+    fn step(&mut self, maze: &mut Maze, _rng: &mut dyn RngCore) -> Option<Wall> {
+        for index in self.indices.by_ref() {
+            let wall = maze.wall_from_index(index);
+            let Some([cell1, cell2]) = maze.wall_cells(&wall) else {
+                continue;
+            };
+
+            if maze.is_excluded(cell1) || maze.is_excluded(cell2) {
+                continue;
+            }
+
+            if self.sets.union(cell1.0, cell2.0) {
+                maze.set_wall(&wall, false);
+                return Some(wall);
+            }
+        }
+
+        None
+    }
+}
+
+/// Wilson's algorithm. Attaches every cell to the maze with a loop-erased random walk, which
+/// samples uniformly from all spanning trees of the maze, at the cost of being slow to get
+/// started on large mazes.
+///
+/// If the maze is biased towards some axes, the random walk is weighted accordingly, so that
+/// every spanning tree is instead sampled with probability proportional to the product of the
+/// axis weights of its passages.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Wilson;
+
+impl MazeGenerator for Wilson {
+    fn start(&self, maze: &Maze, rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper> {
+        let root = maze.random_cell(rng);
+
+        let mut in_tree = vec![false; maze.cell_count()];
+        in_tree[root.0] = true;
+
+        // A random walk would never reach the tree from a piece of the maze cut off by the mask,
+        // so every such piece needs a root of its own.
+        let mut components = DisjointSet::new(maze.cell_count());
+        let mut rooted = vec![false; maze.cell_count()];
+        for cell in maze.cells() {
+            if !maze.is_excluded(cell) {
+                for (_, neighbour_cell) in maze.neighbours(cell) {
+                    components.union(cell.0, neighbour_cell.0);
+                }
+            }
+        }
+        rooted[components.find(root.0)] = true;
+
+        Box::new(WilsonStepper {
+            in_tree,
+            components,
+            rooted,
+            directions: vec![0; maze.cell_count()],
+            index: 0,
+            path: VecDeque::new(),
+        })
+    }
+
+    fn describe(&self) -> String {
+        String::from("Wilson")
+    }
+
+    fn estimate_memory(&self, dimensions: &[usize]) -> usize {
+        let cell_count = saturating_cell_count(dimensions);
+        cell_count
+            .saturating_mul(size_of::<bool>() * 2 + size_of::<usize>() + size_of::<Wall>())
+            .saturating_add(DisjointSet::memory(cell_count))
+    }
+}
+
+struct WilsonStepper {
+    in_tree: Vec<bool>,
+    components: DisjointSet,
+    rooted: Vec<bool>,
+    /// Index into the list returned by [Maze::neighbours] of the direction last taken out of each
+    /// cell. Following these from the start of a walk automatically erases any loop in it.
+    directions: Vec<usize>,
+    /// Index of the next cell to start a walk from.
+    index: usize,
+    /// Walls on the loop-erased walk just found that are yet to be knocked down.
+    path: VecDeque<Wall>,
+}
+
+impl GeneratorStepper for WilsonStepper {
+    fn step(&mut self, maze: &mut Maze, rng: &mut dyn RngCore) -> Option<Wall> {
+        while self.path.is_empty() {
+            let index = self.index;
+            if index == maze.cell_count() {
+                return None;
+            }
+            self.index += 1;
+
+            if self.in_tree[index] || maze.is_excluded(CellId(index)) {
+                continue;
+            }
+
+            let component = self.components.find(index);
+            if !self.rooted[component] {
+                self.rooted[component] = true;
+                self.in_tree[index] = true;
+                continue;
+            }
+
+            let mut cell = CellId(index);
+            let mut neighbours = Vec::new();
+            while !self.in_tree[cell.0] {
+                neighbours.clear();
+                neighbours.extend(maze.neighbours(cell));
+                let direction = maze.choose_neighbour(&neighbours, rng).unwrap();
+                self.directions[cell.0] = direction;
+                cell = neighbours[direction].1;
+            }
+
+            let mut cell = CellId(index);
+            while !self.in_tree[cell.0] {
+                let direction = self.directions[cell.0];
+                self.in_tree[cell.0] = true;
+
+                let (wall, neighbour_cell) = maze.neighbours(cell).nth(direction).unwrap();
+                self.path.push_back(wall);
+                cell = neighbour_cell;
+            }
+        }
+
+        let wall = self.path.pop_front().unwrap();
+        maze.set_wall(&wall, false);
+        Some(wall)
+    }
+}
+
+/// Eller's algorithm, generalized to n dimensions. Sweeps the maze one hyper-slice at a time along
+/// an axis, so that apart from the maze itself only the set membership of a single slice needs to
+/// be kept in memory, which makes it suitable for mazes with one enormous axis.
+///
+/// Within each slice, neighbouring cells in different sets are randomly merged, and then every set
+/// is extended into the next slice at least once. The last slice is merged completely, together
+/// with the first slice across the seam if the axis wraps around, so that the result is still a
+/// perfect maze.
+///
+/// If the mask of the maze cuts off a set from the next slice, the set has to be joined up with
+/// the rest of the maze at the end, which does need memory for the whole maze.
+///
+/// Axis weights scale the probability of merging along each axis within a slice, and of extending
+/// cells into the next slice.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Eller {
+    /// Axis to sweep along, or the longest axis if [None].
+    pub axis: Option<usize>,
+}
+
+impl Eller {
+    /// Probability that two neighbouring cells in different sets within a slice are merged.
+    const MERGE_PROBABILITY: f64 = 0.5;
+
+    /// Probability that a cell is extended into the next slice, in addition to the one cell per
+    /// set that is always extended.
+    const EXTEND_PROBABILITY: f64 = 0.3;
+
+    /// Get the cell with the given index within the slice at layer.
+    fn slice_cell(maze: &Maze, axis: usize, index: usize, layer: usize) -> CellId {
+        let stride = maze.strides[axis];
+        CellId(index / stride * stride * maze.dimensions[axis] + layer * stride + index % stride)
+    }
+
+    /// Inverse of [Eller::slice_cell], ignoring the layer.
+    fn slice_index(maze: &Maze, axis: usize, cell: CellId) -> usize {
+        let stride = maze.strides[axis];
+        cell.0 / (stride * maze.dimensions[axis]) * stride + cell.0 % stride
+    }
+}
+
+impl MazeGenerator for Eller {
+    fn start(&self, maze: &Maze, _rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper> {
+        let axis = self.axis.unwrap_or_else(|| {
+            (0..maze.dimensions.len()).max_by_key(|axis| maze.dimensions[*axis]).unwrap()
+        });
+
+        let layer_count = maze.dimensions[axis];
+        let slice_len = maze.cell_count() / layer_count;
+
+        // Take weights relative to their geometric mean, so that an unbiased maze keeps the base
+        // probabilities, and give an axis with relative weight w that many chances at merging or
+        // extending.
+        let mean_weight = (maze.axis_weights.iter().map(|weight| weight.ln()).sum::<f64>() / maze.axis_weights.len() as f64).exp();
+        let weights = maze.axis_weights.iter().map(|weight| weight / mean_weight).collect::<Vec<_>>();
+
+        // The first half of the elements are the cells in the first slice, which we need to
+        // remember in order to close the seam at the end. The second half of the elements are the
+        // cells in the current slice.
+        let mut sets = DisjointSet::new(slice_len * 2);
+        for index in 0..slice_len {
+            sets.union(index, slice_len + index);
+        }
+
+        Box::new(EllerStepper {
+            axis,
+            layer_count,
+            slice_len,
+            weights,
+            sets,
+            layer: 0,
+            walls: VecDeque::new(),
+        })
+    }
+
+    fn describe(&self) -> String {
+        match self.axis {
+            Some(axis) => format!("Eller (axis {axis})"),
+            None => String::from("Eller"),
+        }
+    }
+
+    fn estimate_memory(&self, dimensions: &[usize]) -> usize {
+        let axis = self.axis.unwrap_or_else(|| {
+            (0..dimensions.len()).max_by_key(|axis| dimensions[*axis]).unwrap()
+        });
+
+        // Only ever a couple of slices worth of sets and candidate walls.
+        let slice_len = saturating_cell_count(dimensions) / dimensions[axis].max(1);
+        let per_cell = dimensions.len() * (size_of::<(Wall, usize, usize)>() + size_of::<Wall>()) + size_of::<usize>() * 4 + size_of::<bool>();
+        DisjointSet::memory(slice_len.saturating_mul(2))
+            .saturating_mul(2)
+            .saturating_add(slice_len.saturating_mul(per_cell))
+    }
+}
+
+struct EllerStepper {
+    axis: usize,
+    layer_count: usize,
+    slice_len: usize,
+    weights: Vec<f64>,
+    sets: DisjointSet,
+    /// Index of the next slice to sweep.
+    layer: usize,
+    /// Walls of the last slice swept that are yet to be knocked down.
+    walls: VecDeque<Wall>,
+}
+
+impl EllerStepper {
+    /// Decide which walls to knock down within the current slice and into the next one.
+    fn sweep(&mut self, maze: &Maze, rng: &mut dyn RngCore) {
+        let (axis, layer, slice_len) = (self.axis, self.layer, self.slice_len);
+        let last = layer == self.layer_count - 1;
+        let probability = |probability: f64, axis: usize| 1.0 - (1.0 - probability).powf(self.weights[axis]);
+
+        let mut candidates = Vec::new();
+        for index in 0..slice_len {
+            let cell = Eller::slice_cell(maze, axis, index, layer);
+            if maze.is_excluded(cell) {
+                continue;
+            }
+
+            for other_axis in 0..maze.dimensions.len() {
+                if other_axis == axis {
+                    continue;
+                }
+
+                // Crossing a twisted edge might take us out of the slice, in which case the wall
+                // is simply left alone.
+                let Some((_, neighbour_cell)) = maze.neighbour(cell, other_axis, true) else { continue; };
+                if maze.coordinate(neighbour_cell, axis) != layer {
+                    continue;
+                }
+
+                let neighbour_index = Eller::slice_index(maze, axis, neighbour_cell);
+                candidates.push((Wall { cell, axis: other_axis }, slice_len + index, slice_len + neighbour_index));
+            }
+
+            if last && let Some((_, neighbour_cell)) = maze.neighbour(cell, axis, true) {
+                let neighbour_index = Eller::slice_index(maze, axis, neighbour_cell);
+                candidates.push((Wall { cell, axis }, slice_len + index, neighbour_index));
+            }
+        }
+
+        candidates.shuffle(rng);
+        for (wall, element1, element2) in candidates {
+            if self.sets.find(element1) == self.sets.find(element2) {
+                continue;
+            }
+
+            if last || rng.random_bool(probability(Eller::MERGE_PROBABILITY, wall.axis)) {
+                self.sets.union(element1, element2);
+                self.walls.push_back(wall);
+            }
+        }
+
+        if last {
+            return;
+        }
+
+        // Only cells that have a neighbour in the next slice can be extended. If a set has none
+        // because of the mask, it is simply left for [Maze::generate] to join up.
+        let mut groups = Vec::<Vec<usize>>::new();
+        let mut group_indices = HashMap::new();
+        for index in 0..slice_len {
+            let cell = Eller::slice_cell(maze, axis, index, layer);
+            if maze.is_excluded(cell) || maze.neighbour(cell, axis, true).is_none() {
+                continue;
+            }
+
+            let group_index = *group_indices.entry(self.sets.find(slice_len + index)).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group_index].push(index);
+        }
+
+        let mut extended = vec![false; slice_len];
+        for group in groups {
+            extended[*group.choose(rng).unwrap()] = true;
+            for index in group {
+                if rng.random_bool(probability(Eller::EXTEND_PROBABILITY, axis)) {
+                    extended[index] = true;
+                }
+            }
+        }
+
+        // Carry the set membership of the first slice and of the extended cells over to the
+        // next slice. Everything else in the current slice can be forgotten.
+        let mut next_sets = DisjointSet::new(slice_len * 2);
+        let mut representatives = HashMap::new();
+        for element in (0..slice_len).chain((0..slice_len).filter(|index| extended[*index]).map(|index| slice_len + index)) {
+            match representatives.entry(self.sets.find(element)) {
+                hash_map::Entry::Occupied(occupied_entry) => { next_sets.union(*occupied_entry.get(), element); },
+                hash_map::Entry::Vacant(vacant_entry) => { vacant_entry.insert(element); },
+            }
+
+            if element >= slice_len {
+                let index = element - slice_len;
+                self.walls.push_back(Wall { cell: Eller::slice_cell(maze, axis, index, layer), axis });
+            }
+        }
+        self.sets = next_sets;
+    }
+}
+
+impl GeneratorStepper for EllerStepper {
+    fn step(&mut self, maze: &mut Maze, rng: &mut dyn RngCore) -> Option<Wall> {
+        while self.walls.is_empty() {
+            if self.layer == self.layer_count {
+                return None;
+            }
+
+            self.sweep(maze, rng);
+            self.layer += 1;
+        }
+
+        let wall = self.walls.pop_front().unwrap();
+        maze.set_wall(&wall, false);
+        Some(wall)
+    }
+}
+
+/// How the growing tree generator picks the next cell to grow from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Newest,
+    Random,
+    Oldest,
+}
+
+impl Selection {
+    pub fn name(self) -> &'static str {
+        match self {
+            Selection::Newest => "newest",
+            Selection::Random => "random",
+            Selection::Oldest => "oldest",
+        }
+    }
+}
+
+/// Weighted mix of [Selection]s, e.g. `newest:75, random:25` to pick the newest cell 75% of the
+/// time and a random cell 25% of the time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectionPolicy {
+    weights: Vec<(Selection, u32)>,
+}
+
+impl SelectionPolicy {
+    /// Create a policy from a list of selections and their weights.
+    ///
+    /// Return [None] if there is no selection with positive weight, or if the weights add up to
+    /// more than fits in a [u32], which is what they are summed in when picking a selection.
+    pub fn new(weights: Vec<(Selection, u32)>) -> Option<Self> {
+        let total = weights.iter().try_fold(0u32, |total, (_, weight)| total.checked_add(*weight))?;
+        if total == 0 {
+            return None;
+        }
+        Some(Self { weights })
+    }
+
+    /// Pick an index into a list of length len, which must be non-zero.
+    fn select(&self, len: usize, rng: &mut dyn RngCore) -> usize {
+        let selection = self.weights.choose_weighted(rng, |(_, weight)| *weight).unwrap().0;
+        match selection {
+            Selection::Newest => len - 1,
+            Selection::Random => rng.random_range(0..len),
+            Selection::Oldest => 0,
+        }
+    }
+}
+
+impl Default for SelectionPolicy {
+    fn default() -> Self {
+        Self { weights: vec![(Selection::Newest, 1)] }
+    }
+}
+
+impl FromStr for SelectionPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let weights = s
+            .split(',')
+            .map(|s| {
+                let (name, weight) = match s.split_once(':') {
+                    Some((name, weight)) => (name.trim(), weight.trim().parse().map_err(|_| ())?),
+                    None => (s.trim(), 1),
+                };
+
+                let selection = match name {
+                    "newest" => Selection::Newest,
+                    "random" => Selection::Random,
+                    "oldest" => Selection::Oldest,
+                    _ => return Err(()),
+                };
+
+                Ok((selection, weight))
+            })
+            .try_collect()?;
+
+        Self::new(weights).ok_or(())
+    }
+}
+
+impl fmt::Display for SelectionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [(selection, _)] = self.weights.as_slice() {
+            return write!(f, "{}", selection.name());
+        }
+
+        for (i, (selection, weight)) in self.weights.iter().enumerate() {
+            if i != 0 { write!(f, ", ")?; }
+            write!(f, "{}:{}", selection.name(), weight)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Growing tree algorithm. Keeps a list of active cells, and repeatedly grows the maze from one of
+/// them picked according to the selection policy. Always picking the newest cell behaves like the
+/// recursive backtracker, while always picking a random cell behaves like Prim.
+#[derive(Debug, Clone, Default)]
+pub struct GrowingTree {
+    pub policy: SelectionPolicy,
+}
+
+impl MazeGenerator for GrowingTree {
+    fn start(&self, maze: &Maze, rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper> {
+        let root = maze.random_cell(rng);
+
+        let mut visited = vec![false; maze.cell_count()];
+        visited[root.0] = true;
+
+        let active = VecDeque::from([root]);
+
+        Box::new(GrowingTreeStepper { policy: self.policy.clone(), visited, active })
+    }
+
+    fn describe(&self) -> String {
+        format!("Growing Tree ({})", self.policy)
+    }
+
+    fn estimate_memory(&self, dimensions: &[usize]) -> usize {
+        let cell_count = saturating_cell_count(dimensions);
+        cell_count.saturating_add(cell_count.saturating_mul(size_of::<CellId>()))
+    }
+}
+
+struct GrowingTreeStepper {
+    policy: SelectionPolicy,
+    visited: Vec<bool>,
+    active: VecDeque<CellId>,
+}
+
+impl GeneratorStepper for GrowingTreeStepper {
+    fn step(&mut self, maze: &mut Maze, rng: &mut dyn RngCore) -> Option<Wall> {
+        while !self.active.is_empty() {
+            let index = self.policy.select(self.active.len(), rng);
+
+            let candidates = maze
+                .neighbours(self.active[index])
+                .filter(|(_, neighbour_cell)| !self.visited[neighbour_cell.0])
+                .collect::<Vec<_>>();
+
+            let Some(candidate_index) = maze.choose_neighbour(&candidates, rng) else {
+                self.active.remove(index);
+                continue;
+            };
+
+            let (wall, neighbour_cell) = candidates[candidate_index];
+            maze.set_wall(&wall, false);
+            self.visited[neighbour_cell.0] = true;
+            self.active.push_back(neighbour_cell);
+            return Some(wall);
+        }
+
+        None
+    }
+}
+
+/// Recursive division. Starts with every wall open, and repeatedly splits a box of the maze in two
+/// with a hyperplane of walls that has a single gap in it, which gives long straight walls and a
+/// blocky look.
+///
+/// Boxes are only split across axes longer than the room size, so anything above 1 leaves open
+/// rooms behind and the maze is no longer perfect. The whole maze is treated as a single box, so
+/// the walls across the seam of every axis that wraps around are put up first.
+///
+/// Axis weights make it less likely to split a box across an axis, so that walls along it are
+/// more likely to stay open.
+#[derive(Debug, Clone, Copy)]
+pub struct RecursiveDivision {
+    /// Longest side of a box that is left open as a room.
+    pub room_size: usize,
+}
+
+impl Default for RecursiveDivision {
+    fn default() -> Self {
+        Self { room_size: 1 }
+    }
+}
+
+/// Iterate over the cells in the box of the maze with the given origin and size.
+fn box_cells<'a>(maze: &'a Maze, origin: &'a [usize], size: &'a [usize]) -> impl Iterator<Item = CellId> + 'a {
+    (0..size.iter().product::<usize>()).map(move |mut index| {
+        let mut cell = 0;
+        for axis in 0..size.len() {
+            cell += (origin[axis] + index % size[axis]) * maze.strides[axis];
+            index /= size[axis];
+        }
+        CellId(cell)
+    })
+}
+
+impl MazeGenerator for RecursiveDivision {
+    fn start(&self, maze: &Maze, _rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper> {
+        let mut walls = VecDeque::new();
+        for axis in 0..maze.dimensions.len() {
+            let mut origin = vec![0; maze.dimensions.len()];
+            let mut size = maze.dimensions.clone();
+            origin[axis] = maze.dimensions[axis] - 1;
+            size[axis] = 1;
+            walls.extend(box_cells(maze, &origin, &size).map(|cell| Wall { cell, axis }));
+        }
+
+        Box::new(RecursiveDivisionStepper {
+            room_size: self.room_size.max(1),
+            boxes: vec![(vec![0; maze.dimensions.len()], maze.dimensions.clone())],
+            walls,
+        })
+    }
+
+    fn starts_open(&self) -> bool {
+        true
+    }
+
+    fn describe(&self) -> String {
+        if self.room_size <= 1 {
+            String::from("Recursive Division")
+        } else {
+            format!("Recursive Division (rooms up to {})", self.room_size)
+        }
+    }
+
+    fn estimate_memory(&self, dimensions: &[usize]) -> usize {
+        // The seams of every axis are queued up at once, and a hyperplane is never any larger.
+        let wall_count = saturating_cell_count(dimensions).saturating_mul(dimensions.len());
+        wall_count.saturating_mul(size_of::<Wall>())
+    }
+}
+
+struct RecursiveDivisionStepper {
+    room_size: usize,
+    /// Origin and size of the boxes yet to be split.
+    boxes: Vec<(Vec<usize>, Vec<usize>)>,
+    /// Walls on the hyperplane just picked that are yet to be put up.
+    walls: VecDeque<Wall>,
+}
+
+impl GeneratorStepper for RecursiveDivisionStepper {
+    fn step(&mut self, maze: &mut Maze, rng: &mut dyn RngCore) -> Option<Wall> {
+        loop {
+            // Walls of excluded cells and across the edge of a bounded axis are up already.
+            while let Some(wall) = self.walls.pop_front() {
+                if !maze.get_wall(&wall) {
+                    maze.set_wall(&wall, true);
+                    return Some(wall);
+                }
+            }
+
+            let (origin, size) = self.boxes.pop()?;
+            let weights = (0..size.len()).map(|axis| {
+                if size[axis] > self.room_size {
+                    (size[axis] - 1) as f64 / maze.axis_weights[axis]
+                } else {
+                    0.0
+                }
+            });
+
+            // Every side is short enough for the box to be left as a room.
+            let Ok(distribution) = WeightedIndex::new(weights) else {
+                continue;
+            };
+
+            let axis = distribution.sample(rng);
+            let split = rng.random_range(1..size[axis]);
+
+            let mut plane_origin = origin.clone();
+            let mut plane_size = size.clone();
+            plane_origin[axis] += split - 1;
+            plane_size[axis] = 1;
+
+            let mut plane = box_cells(maze, &plane_origin, &plane_size).collect::<Vec<_>>();
+            plane.swap_remove(rng.random_range(0..plane.len()));
+            plane.sort();
+            self.walls.extend(plane.into_iter().map(|cell| Wall { cell, axis }));
+
+            let mut first_size = size.clone();
+            let mut second_origin = origin.clone();
+            let mut second_size = size;
+            first_size[axis] = split;
+            second_origin[axis] += split;
+            second_size[axis] -= split;
+            self.boxes.push((origin, first_size));
+            self.boxes.push((second_origin, second_size));
+        }
+    }
+}
+
+/// Generators selectable from the setup screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratorKind {
+    Prim,
+    RecursiveBacktracker,
+    Kruskal,
+    Wilson,
+    Eller,
+    GrowingTree,
+    RecursiveDivision,
+}
+
+impl GeneratorKind {
+    pub const ALL: [GeneratorKind; 7] = [
+        GeneratorKind::Prim,
+        GeneratorKind::RecursiveBacktracker,
+        GeneratorKind::Kruskal,
+        GeneratorKind::Wilson,
+        GeneratorKind::Eller,
+        GeneratorKind::GrowingTree,
+        GeneratorKind::RecursiveDivision,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GeneratorKind::Prim => "Prim",
+            GeneratorKind::RecursiveBacktracker => "Recursive Backtracker",
+            GeneratorKind::Kruskal => "Kruskal",
+            GeneratorKind::Wilson => "Wilson",
+            GeneratorKind::Eller => "Eller",
+            GeneratorKind::GrowingTree => "Growing Tree",
+            GeneratorKind::RecursiveDivision => "Recursive Division",
+        }
+    }
+
+    /// Cycle to the next generator, wrapping around if necessary.
+    pub fn next(self) -> GeneratorKind {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Cycle to the previous generator, wrapping around if necessary.
+    pub fn prev(self) -> GeneratorKind {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap();
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Construct the generator. The selection policy is only used by the growing tree generator,
+    /// and the room size only by the recursive division generator.
+    pub fn generator(self, policy: SelectionPolicy, room_size: usize) -> Box<dyn MazeGenerator> {
+        match self {
+            GeneratorKind::Prim => Box::new(Prim),
+            GeneratorKind::RecursiveBacktracker => Box::new(RecursiveBacktracker),
+            GeneratorKind::Kruskal => Box::new(Kruskal),
+            GeneratorKind::Wilson => Box::new(Wilson),
+            GeneratorKind::Eller => Box::new(Eller::default()),
+            GeneratorKind::GrowingTree => Box::new(GrowingTree { policy }),
+            GeneratorKind::RecursiveDivision => Box::new(RecursiveDivision { room_size }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Boundary;
+    use crate::bitset::BitSet;
+
+    use std::collections::{HashMap, HashSet};
+
+    /// Check that every piece of the maze left by the mask is a spanning tree, i.e. every cell is
+    /// reachable from every other cell in the same piece and there are no loops, and that the end
+    /// can be reached from the start.
+    fn check_perfect(maze: &Maze) {
+        let mut pieces = DisjointSet::new(maze.cell_count());
+        let mut trees = DisjointSet::new(maze.cell_count());
+        let mut piece_count = 0;
+        let mut tree_count = 0;
+        let mut cell_count = 0;
+
+        for index in 0..maze.walls.len() {
+            let wall = maze.wall_from_index(index);
+            let Some([cell1, cell2]) = maze.wall_cells(&wall) else {
+                assert!(maze.get_wall(&wall));
+                continue;
+            };
+
+            if maze.is_excluded(cell1) || maze.is_excluded(cell2) {
+                assert!(maze.get_wall(&wall));
+                continue;
+            }
+
+            piece_count += pieces.union(cell1.0, cell2.0) as usize;
+            if !maze.get_wall(&wall) {
+                assert!(trees.union(cell1.0, cell2.0), "loop");
+                tree_count += 1;
+            }
+        }
+
+        for cell in maze.cells() {
+            if !maze.is_excluded(cell) {
+                cell_count += 1;
+            }
+        }
+
+        assert_eq!(piece_count, tree_count, "pieces not connected");
+        assert!(cell_count > 0);
+
+        assert!(!maze.is_excluded(maze.start));
+        assert!(!maze.is_excluded(maze.end));
+        assert_eq!(trees.find(maze.start.0), trees.find(maze.end.0));
+    }
+
+    #[test]
+    fn test() {
+        let mut rng = StdRng::seed_from_u64(0x9b1f3c27d84e06a5);
+        for kind in GeneratorKind::ALL {
+            for dimensions in [vec![1], vec![2, 3], vec![5, 4], vec![3, 4, 5], vec![2, 2, 2, 2]] {
+                let mut maze = Maze::new(dimensions);
+                maze.generate(kind.generator(SelectionPolicy::default(), 1).as_ref(), &mut rng);
+                check_perfect(&maze);
+            }
+        }
+    }
+
+    #[test]
+    fn test_growing_tree_policy() {
+        let mut rng = StdRng::seed_from_u64(0xc84d2a6f1e7b9035);
+        for policy in ["newest", "random", "oldest", "newest:75, random:25", "random:1,oldest:3", "newest:4294967295, random:0"] {
+            let policy = policy.parse::<SelectionPolicy>().unwrap();
+            assert_eq!(policy.to_string().parse::<SelectionPolicy>(), Ok(policy.clone()));
+
+            let mut maze = Maze::new(vec![3, 4, 5]);
+            maze.generate(&GrowingTree { policy }, &mut rng);
+            check_perfect(&maze);
+        }
+
+        for policy in ["", "newest:", "newest:0", "latest", "newest:-1", "newest:4294967295, random:1"] {
+            assert!(policy.parse::<SelectionPolicy>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_stepper() {
+        for kind in GeneratorKind::ALL {
+            let mut rng = StdRng::seed_from_u64(0x2f9c4e71b0a8d635);
+            let mut maze = Maze::new(vec![3, 4, 5]);
+            maze.set_mask_with(|position| position != [1, 1, 1]);
+
+            // Every step knocks down exactly one new wall, or puts one up if the generator starts
+            // open.
+            let generator = kind.generator(SelectionPolicy::default(), 1);
+            let mut stepper = maze.start_generation(generator.as_ref(), &mut rng);
+            let initial_count = maze.walls.count_ones();
+            let mut walls = HashSet::new();
+            while let Some(wall) = stepper.step(&mut maze, &mut rng) {
+                assert_eq!(maze.get_wall(&wall), generator.starts_open());
+                assert!(walls.insert(maze.compute_wall_index(&wall)));
+            }
+            assert_eq!(maze.walls.count_ones().abs_diff(initial_count), walls.len());
+
+            // Stepping through must give the same result as generating in one go.
+            maze.finish_generation(&mut rng);
+            check_perfect(&maze);
+
+            let mut rng = StdRng::seed_from_u64(0x2f9c4e71b0a8d635);
+            let mut other_maze = Maze::new(vec![3, 4, 5]);
+            other_maze.set_mask_with(|position| position != [1, 1, 1]);
+            other_maze.generate(generator.as_ref(), &mut rng);
+            assert_eq!(maze.walls, other_maze.walls);
+            assert_eq!(maze.start, other_maze.start);
+            assert_eq!(maze.end, other_maze.end);
+        }
+    }
+
+    #[test]
+    fn test_eller_axis() {
+        let mut rng = StdRng::seed_from_u64(0x6a04e1bd93c7f258);
+        for axis in 0..3 {
+            let mut maze = Maze::new(vec![3, 4, 5]);
+            maze.generate(&Eller { axis: Some(axis) }, &mut rng);
+            check_perfect(&maze);
+        }
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let mut rng = StdRng::seed_from_u64(0x84f2b6c01ad9e357);
+        let boundaries = [
+            vec![Boundary::Bounded, Boundary::Bounded, Boundary::Bounded],
+            vec![Boundary::Wrap, Boundary::Bounded, Boundary::Twisted(0)],
+            vec![Boundary::Twisted(1), Boundary::Twisted(2), Boundary::Twisted(0)],
+        ];
+
+        for kind in GeneratorKind::ALL {
+            for boundaries in boundaries.iter() {
+                let mut maze = Maze::with_boundaries(vec![3, 4, 5], boundaries.clone());
+                maze.generate(kind.generator(SelectionPolicy::default(), 1).as_ref(), &mut rng);
+                check_perfect(&maze);
+            }
+        }
+    }
+
+    #[test]
+    fn test_axis_weights() {
+        let mut rng = StdRng::seed_from_u64(0x3e9a7c15d2f0846b);
+        for kind in GeneratorKind::ALL {
+            let mut maze = Maze::new(vec![12, 12]);
+            maze.set_axis_weights(vec![8.0, 1.0]);
+            maze.generate(kind.generator(SelectionPolicy::default(), 1).as_ref(), &mut rng);
+            check_perfect(&maze);
+
+            let open_count0 = (0..maze.cell_count()).filter(|index| !maze.walls.get(*index)).count();
+            let open_count1 = (maze.cell_count()..maze.walls.len()).filter(|index| !maze.walls.get(*index)).count();
+            assert!(open_count0 > open_count1 * 2, "{}: {open_count0} vs {open_count1}", kind.name());
+        }
+    }
+
+    #[test]
+    fn test_extreme_axis_weights() {
+        let mut rng = StdRng::seed_from_u64(0x71f0c3a9e25b48d6);
+        let (min, max) = (*Maze::AXIS_WEIGHT_RANGE.start(), *Maze::AXIS_WEIGHT_RANGE.end());
+        for kind in GeneratorKind::ALL {
+            for axis_weights in [vec![max, max, max], vec![min, min, min], vec![max, min, max]] {
+                let mut maze = Maze::new(vec![6, 5, 4]);
+                maze.set_axis_weights(axis_weights);
+                maze.generate(kind.generator(SelectionPolicy::default(), 1).as_ref(), &mut rng);
+                check_perfect(&maze);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mask() {
+        let mut rng = StdRng::seed_from_u64(0xa7b3190d5ce642f8);
+        for kind in GeneratorKind::ALL {
+            // Annulus, which splits into two pieces along the shortest axis.
+            let mut maze = Maze::new(vec![12, 4, 10]);
+            maze.set_mask_with(|position| {
+                let radius = position.iter().map(|value| (*value as f64 - 4.5).powi(2)).sum::<f64>();
+                (4.0..=30.0).contains(&radius)
+            });
+            maze.generate(kind.generator(SelectionPolicy::default(), 1).as_ref(), &mut rng);
+            check_perfect(&maze);
+
+            // Random mask, which splits into lots of pieces.
+            let mut maze = Maze::with_boundaries(vec![6, 7, 5], vec![Boundary::Bounded, Boundary::Twisted(2), Boundary::Wrap]);
+            maze.set_mask((0..maze.cell_count()).map(|_| rng.random_bool(0.6)).collect());
+            maze.generate(kind.generator(SelectionPolicy::default(), 1).as_ref(), &mut rng);
+            check_perfect(&maze);
+        }
+    }
+
+    #[test]
+    fn test_recursive_division_rooms() {
+        let mut rng = StdRng::seed_from_u64(0x1d6b8e3f02a7c594);
+        for room_size in [2, 3, 5] {
+            let mut maze = Maze::with_boundaries(vec![12, 9, 7], vec![Boundary::Wrap, Boundary::Bounded, Boundary::Twisted(0)]);
+            maze.generate(&RecursiveDivision { room_size }, &mut rng);
+
+            // Rooms leave loops behind, but every cell is still reachable.
+            assert_eq!(maze.reachable(maze.start).len(), maze.cell_count());
+            let open_count = maze.walls.len() - maze.walls.count_ones();
+            assert!(open_count > maze.cell_count() - 1);
+
+            // Walls across the seam of an axis that wraps around are always up, since the maze is
+            // divided as a single box.
+            for cell in maze.cells() {
+                if maze.coordinate(cell, 0) == 11 {
+                    assert!(maze.get_wall(&Wall { cell, axis: 0 }));
+                }
+                if maze.coordinate(cell, 2) == 6 {
+                    assert!(maze.get_wall(&Wall { cell, axis: 2 }));
+                }
+            }
+        }
+
+        // Rooms as large as the maze leave nothing to divide.
+        let mut maze = Maze::with_boundaries(vec![4, 4], vec![Boundary::Bounded, Boundary::Bounded]);
+        maze.generate(&RecursiveDivision { room_size: 4 }, &mut rng);
+        assert_eq!(maze.walls.len() - maze.walls.count_ones(), 24);
+    }
+
+    #[test]
+    fn test_wilson_uniform() {
+        let mut rng = StdRng::seed_from_u64(0x2f7d0e94c1a85b36);
+
+        // Number of spanning trees of the 2x3 torus, from Kirchhoff's matrix tree theorem. Note that
+        // along the axis of length 2, the two cells are separated by two distinct walls.
+        const TREE_COUNT: usize = 294;
+        const SAMPLE_COUNT: usize = TREE_COUNT * 100;
+
+        let mut counts = HashMap::<BitSet, usize>::new();
+        let mut maze = Maze::new(vec![2, 3]);
+        for _ in 0..SAMPLE_COUNT {
+            maze.generate(&Wilson, &mut rng);
+            *counts.entry(maze.walls.clone()).or_default() += 1;
+        }
+
+        for walls in counts.keys() {
+            maze.walls = walls.clone();
+            check_perfect(&maze);
+        }
+        assert_eq!(counts.len(), TREE_COUNT);
+
+        // Pearson's chi-squared test against the uniform distribution, with a generous threshold of
+        // about 6 standard deviations above the mean.
+        let expected = (SAMPLE_COUNT / TREE_COUNT) as f64;
+        let statistic = counts.values().map(|count| (*count as f64 - expected).powi(2) / expected).sum::<f64>();
+        let degrees_of_freedom = (TREE_COUNT - 1) as f64;
+        assert!(statistic < degrees_of_freedom + 6.0 * (2.0 * degrees_of_freedom).sqrt(), "statistic = {statistic}");
+    }
+}
@@ -1,8 +1,23 @@
 #![feature(iterator_try_collect)]
+#![cfg_attr(test, feature(test))]
+
+#[cfg(test)]
+extern crate test;
 
 pub mod binary_heap;
+pub mod bitset;
+pub mod disjoint_set;
+pub mod generator;
+pub mod solver;
+pub mod stats;
+
+#[cfg(test)]
+mod benches;
+#[cfg(test)]
+mod test_support;
 
 use rand::prelude::*;
+use rand::distr::weighted::WeightedIndex;
 
 use ratatui::{prelude::*, widgets::{Block, Paragraph}};
 use layout::Position;
@@ -12,295 +27,984 @@ use std::collections::{HashMap, HashSet};
 
 use crossterm::event::*;
 
-use crate::binary_heap::{BinaryHashHeap, BinaryHashHeapItem, PushAction};
+use crate::bitset::BitSet;
+use crate::disjoint_set::DisjointSet;
+use crate::generator::{GeneratorKind, GeneratorStepper, MazeGenerator, SelectionPolicy};
+use crate::solver::{AStar, NoPath, Progress, SearchStep, Solver, SolverKind, SolverStepper};
+use crate::stats::MazeStats;
+
+/// Peak number of bytes needed for a maze, as returned by [Maze::estimate_memory], split up by
+/// what it is needed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    /// Generating the maze and picking the start and end.
+    pub generating: usize,
+    /// Solving the maze, which is only done on request unless doors, one-way passages or portals
+    /// have to be placed along the solution.
+    pub solving: usize,
+    /// Keeping the heat map around on top of either of the above, which is only done on request.
+    pub heat_map: usize,
+}
 
-#[derive(PartialEq, Eq, Hash)]
-struct Wall {
-    position: Vec<usize>,
-    axis: usize,
+impl MemoryEstimate {
+    /// Bytes needed before the maze can be shown, with placing set if doors, one-way passages or
+    /// portals are to be placed.
+    pub fn required(&self, placing: bool) -> usize {
+        if placing {
+            self.generating.max(self.solving)
+        } else {
+            self.generating
+        }
+    }
+
+    /// Bytes needed at worst, while solving with the heat map shown.
+    pub fn peak(&self) -> usize {
+        self.generating.max(self.solving).saturating_add(self.heat_map)
+    }
 }
 
-impl Wall {
-    /// Get the list of walls neighbouring a cell at position.
-    pub fn from_cell(shape: &[usize], position: &[usize]) -> Vec<Wall> {
-        let mut walls = Vec::new();
-        for axis in 0..shape.len() {
-            for sign in [false, true] {
-                let mut position = position.to_vec();
-                if sign {
-                    if position[axis] != 0 {
-                        position[axis] -= 1;
-                    } else {
-                        position[axis] = shape[axis] - 1;
-                    }
-                }
-                walls.push(Wall { position, axis });
-            }
+/// Behaviour of an axis when walking off either end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /// Wrap around to the other end, as on a torus.
+    Wrap,
+    /// The edge is a permanent wall.
+    Bounded,
+    /// Wrap around to the other end while mirroring the given axis, as on a Möbius strip or a Klein
+    /// bottle.
+    Twisted(usize),
+}
+
+impl std::fmt::Display for Boundary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Boundary::Wrap => write!(f, "wrap"),
+            Boundary::Bounded => write!(f, "bounded"),
+            Boundary::Twisted(axis) => write!(f, "twisted:{axis}"),
         }
-        walls
     }
+}
 
-    /// Get the two cells neighbouring the wall.
-    pub fn get_neighbour_cells(&self, shape: &[usize]) -> [Vec<usize>; 2] {
-        let position1 = self.position.clone();
+impl std::str::FromStr for Boundary {
+    type Err = ();
 
-        let mut position2 = self.position.clone();
-        if position2[self.axis] != shape[self.axis] - 1 {
-            position2[self.axis] += 1;
-        } else {
-            position2[self.axis] = 0;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "wrap" => Ok(Boundary::Wrap),
+            "bounded" => Ok(Boundary::Bounded),
+            s => match s.split_once(':') {
+                Some(("twisted", axis)) => axis.trim().parse().map(Boundary::Twisted).map_err(|_| ()),
+                _ => Err(()),
+            },
+        }
+    }
+}
+
+/// How the start and end of a maze are picked after it has been carved out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Pick both uniformly at random, so the end might be very close to or even on the start.
+    Random,
+    /// Pick the start uniformly at random, and the end uniformly at random among the cells that
+    /// are at least the given number of steps away from it, or the farthest cell if there are none.
+    MinDistance(usize),
+    /// Pick a pair of cells far apart, by going to the farthest cell from a random cell and then
+    /// to the farthest cell from there. In a perfect maze this is a pair that are farthest apart,
+    /// but the loops around rooms left by [generator::RecursiveDivision] can leave pairs farther
+    /// apart still.
+    Diameter,
+}
+
+impl std::fmt::Display for Placement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Placement::Random => write!(f, "random"),
+            Placement::MinDistance(distance) => write!(f, "min:{distance}"),
+            Placement::Diameter => write!(f, "diameter"),
         }
+    }
+}
 
-        [position1, position2]
+impl std::str::FromStr for Placement {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "random" => Ok(Placement::Random),
+            "diameter" => Ok(Placement::Diameter),
+            s => match s.split_once(':') {
+                Some(("min", distance)) => distance.trim().parse().map(Placement::MinDistance).map_err(|_| ()),
+                _ => Err(()),
+            },
+        }
     }
 }
 
-struct Maze {
+/// Linear index of a cell, such that the id of a cell is the same as the wall index of the wall in
+/// the positive direction along axis 0 of the cell.
+///
+/// Moving around only takes a bit of arithmetic on the id, so positions are only ever converted to
+/// coordinates with [Maze::coordinates] for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CellId(pub usize);
+
+/// Wall in the positive direction along axis of a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Wall {
+    cell: CellId,
+    axis: usize,
+}
+
+pub struct Maze {
     dimensions: Vec<usize>,
+    boundaries: Vec<Boundary>,
+
+    /// Difference in cell id between neighbouring cells along each axis.
+    strides: Vec<usize>,
+
+    start: CellId,
+    end: CellId,
 
-    start: Vec<usize>,
-    end: Vec<usize>,
+    /// Cells that must be visited in order before reaching the end counts.
+    checkpoints: Vec<CellId>,
+    /// Number of checkpoints visited so far on the walk from the start.
+    checkpoints_reached: usize,
 
-    position: Vec<usize>,
+    /// Colour of each locked door, by the open wall it sits in. Colours index into
+    /// [Maze::keys].
+    doors: HashMap<Wall, usize>,
+    /// Cell of the key of each colour.
+    keys: Vec<CellId>,
+    /// Bitmask of the colours of the keys collected so far on the walk from the start.
+    keys_held: u32,
+
+    /// Pairs of cells joined by a portal, so that stepping onto either moves on to the other.
+    portals: Vec<[CellId; 2]>,
+
+    position: CellId,
     axes: [usize; 2],
 
-    walls: Vec<bool>,
+    walls: BitSet,
+
+    /// Whether each open wall, by wall index, can only be passed through in one direction.
+    one_way: BitSet,
+    /// Whether each one-way wall, by wall index, can only be passed through in the positive
+    /// direction along its axis rather than the negative one.
+    one_way_positive: BitSet,
+
+    /// Relative likelihood of knocking down a wall along each axis during generation.
+    axis_weights: Vec<f64>,
+
+    /// Whether each cell, by cell id, is part of the maze, or [None] if every cell is.
+    mask: Option<BitSet>,
+
+    /// How the start and end are picked during generation.
+    placement: Placement,
+
+    /// Description of the generator used to generate the maze.
+    generator: String,
+}
+
+/// Number of cells in a maze with the given dimensions, saturating instead of overflowing.
+fn saturating_cell_count(dimensions: &[usize]) -> usize {
+    dimensions.iter().fold(1, |count, limit| count.saturating_mul(*limit))
 }
 
 impl Maze {
     pub fn new(dimensions: Vec<usize>) -> Maze {
-        let start = vec![0; dimensions.len()];
-        let end = vec![0; dimensions.len()];
-        let position = vec![0; dimensions.len()];
+        let boundaries = vec![Boundary::Wrap; dimensions.len()];
+        Self::with_boundaries(dimensions, boundaries)
+    }
+
+    /// Create a maze with the given boundary behaviour for each axis.
+    pub fn with_boundaries(dimensions: Vec<usize>, boundaries: Vec<Boundary>) -> Maze {
+        assert_eq!(dimensions.len(), boundaries.len());
+        for (axis, boundary) in boundaries.iter().enumerate() {
+            if let Boundary::Twisted(mirror_axis) = boundary {
+                assert!(*mirror_axis != axis && *mirror_axis < dimensions.len());
+            }
+        }
+
+        let strides = dimensions
+            .iter()
+            .scan(1, |stride, limit| {
+                let result = *stride;
+                *stride *= *limit;
+                Some(result)
+            })
+            .collect();
+
+        let start = CellId(0);
+        let end = CellId(0);
+        let position = CellId(0);
 
         let axes = [0, 1];
 
         let wall_count = dimensions.iter().product::<usize>() * dimensions.len();
-        let walls = vec![true; wall_count];
+        let walls = BitSet::new(wall_count, true);
+        let one_way = BitSet::new(wall_count, false);
+        let one_way_positive = BitSet::new(wall_count, false);
+
+        let axis_weights = vec![1.0; dimensions.len()];
 
         Maze {
             dimensions,
+            boundaries,
+            strides,
             start,
             end,
+            checkpoints: Vec::new(),
+            checkpoints_reached: 0,
+            doors: HashMap::new(),
+            keys: Vec::new(),
+            keys_held: 0,
+            portals: Vec::new(),
             position,
             axes,
             walls,
+            one_way,
+            one_way_positive,
+            axis_weights,
+            mask: None,
+            placement: Placement::Random,
+            generator: String::new(),
         }
     }
 
-    pub fn compute_wall_index(&self, wall: &Wall) -> usize {
-        let mut index = 0;
-        let mut stride = 1;
+    /// Set how the start and end are picked during generation.
+    pub fn set_placement(&mut self, placement: Placement) {
+        self.placement = placement;
+    }
+
+    /// Exclude every cell whose bit in the bitmap over cell ids is clear from the maze. At least
+    /// one cell must remain.
+    pub fn set_mask(&mut self, mask: BitSet) {
+        assert_eq!(mask.len(), self.cell_count());
+        assert!(mask.count_ones() > 0);
+        self.mask = Some(mask);
+    }
 
-        for (limit, value) in std::iter::zip(self.dimensions.iter(), wall.position.iter()) {
-            index += stride * *value;
-            stride *= *limit;
+    /// Exclude every cell for which the predicate on its coordinates returns false from the maze.
+    /// At least one cell must remain.
+    pub fn set_mask_with(&mut self, predicate: impl Fn(&[usize]) -> bool) {
+        let mask = self.cells().map(|cell| predicate(&self.coordinates(cell))).collect();
+        self.set_mask(mask);
+    }
+
+    /// Check if a cell has been excluded from the maze by the mask.
+    ///
+    /// Excluded cells are never visited by generators, and all of their walls stay up.
+    pub fn is_excluded(&self, cell: CellId) -> bool {
+        match &self.mask {
+            Some(mask) => !mask.get(cell.0),
+            None => false,
         }
+    }
 
-        index += stride * wall.axis;
-        index
+    /// Smallest and largest axis weight. Generators multiply and divide by the weights and add up
+    /// the results, which must stay finite, and a random walk that hardly ever moves along some
+    /// axis takes forever to get anywhere.
+    pub const AXIS_WEIGHT_RANGE: std::ops::RangeInclusive<f64> = 1e-2..=1e2;
+
+    /// Set the relative likelihood of knocking down a wall along each axis during generation,
+    /// which must all lie in [Maze::AXIS_WEIGHT_RANGE].
+    pub fn set_axis_weights(&mut self, axis_weights: Vec<f64>) {
+        assert_eq!(axis_weights.len(), self.dimensions.len());
+        assert!(axis_weights.iter().all(|weight| Self::AXIS_WEIGHT_RANGE.contains(weight)));
+        self.axis_weights = axis_weights;
     }
 
-    /// Travel one square in the given axis in either positive or negative direction depending on
-    /// given sign, wrapping around if necessary.
-    pub fn traverse_inplace(&self, position: &mut [usize], axis: usize, sign: bool) {
-        if sign {
-            if position[axis] != self.dimensions[axis] - 1 {
-                position[axis] += 1;
-            } else {
-                position[axis] = 0;
-            }
-        } else {
-            if position[axis] != 0 {
-                position[axis] -= 1;
-            } else {
-                position[axis] = self.dimensions[axis] - 1;
-            }
+    /// Check if walls along any axis are more likely to be knocked down than others.
+    pub fn is_axis_biased(&self) -> bool {
+        self.axis_weights.iter().any(|weight| *weight != self.axis_weights[0])
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.dimensions.iter().product()
+    }
+
+    /// Estimate the peak number of bytes needed to generate and solve a maze with the given
+    /// dimensions, number of checkpoints and number of doors, without allocating anything. This
+    /// saturates instead of overflowing, so it is safe to call on dimensions that could never be
+    /// allocated.
+    pub fn estimate_memory(dimensions: &[usize], masked: bool, checkpoints: usize, doors: usize, generator: &dyn MazeGenerator) -> MemoryEstimate {
+        let cell_count = saturating_cell_count(dimensions);
+        let wall_count = cell_count.saturating_mul(dimensions.len());
+
+        // Walls, which of them are one-way passages in which direction, and the mask.
+        let maze = BitSet::memory(wall_count)
+            .saturating_mul(3)
+            .saturating_add(if masked { BitSet::memory(cell_count) } else { 0 });
+
+        // Connecting components holds a candidate for every wall on top of the sets, and
+        // placement keeps a distance for every cell reachable from the start.
+        let mut finishing = cell_count.saturating_mul(size_of::<bool>() + size_of::<(CellId, usize)>());
+        if masked {
+            finishing = finishing.max(DisjointSet::memory(cell_count)
+                .saturating_add(wall_count.saturating_mul(size_of::<(Wall, CellId, CellId)>())));
+        }
+
+        // The search keeps a visited flag and a link for every state of a cell, the keys held and
+        // the checkpoints reached, and in the worst case every state is on the heap, which also
+        // indexes its entries.
+        let state_count = cell_count
+            .saturating_mul(1 << doors.min(u32::BITS as usize))
+            .saturating_mul(checkpoints.saturating_add(1));
+        let solving = state_count.saturating_mul(size_of::<bool>() + size_of::<usize>() + 64);
+
+        // The heat map keeps the distances from the end and from the player around, and needs a
+        // queue while working them out.
+        let heat_map = cell_count.saturating_mul(size_of::<Option<usize>>() * 2 + size_of::<CellId>());
+
+        MemoryEstimate {
+            generating: maze.saturating_add(generator.estimate_memory(dimensions).max(finishing)),
+            solving: maze.saturating_add(solving),
+            heat_map,
         }
     }
 
+    /// Iterate over every cell, including excluded ones, in order of id.
+    pub fn cells(&self) -> impl Iterator<Item = CellId> + use<> {
+        (0..self.cell_count()).map(CellId)
+    }
+
+    /// Get the id of the cell at position.
+    pub fn cell_id(&self, position: &[usize]) -> CellId {
+        CellId(std::iter::zip(self.strides.iter(), position.iter()).map(|(stride, value)| stride * value).sum())
+    }
+
+    /// Inverse of [Maze::cell_id].
+    pub fn coordinates(&self, cell: CellId) -> Vec<usize> {
+        (0..self.dimensions.len()).map(|axis| self.coordinate(cell, axis)).collect()
+    }
+
+    /// Get a single coordinate of a cell.
+    pub fn coordinate(&self, cell: CellId, axis: usize) -> usize {
+        cell.0 / self.strides[axis] % self.dimensions[axis]
+    }
+
+    pub fn compute_wall_index(&self, wall: &Wall) -> usize {
+        wall.cell.0 + self.cell_count() * wall.axis
+    }
+
+    /// Inverse of [Maze::compute_wall_index].
+    pub fn wall_from_index(&self, index: usize) -> Wall {
+        let cell_count = self.cell_count();
+        Wall { cell: CellId(index % cell_count), axis: index / cell_count }
+    }
+
     /// Travel one square in the given axis in either positive or negative direction depending on
-    /// given sign, wrapping around if necessary.
-    pub fn traverse(&self, position: &[usize], axis: usize, sign: bool) -> Vec<usize> {
-        let mut result = position.to_vec();
-        self.traverse_inplace(&mut result, axis, sign);
-        result
+    /// given sign, wrapping around according to the boundary of the axis if necessary.
+    ///
+    /// Return [None] if we would walk off a bounded edge.
+    pub fn traverse(&self, cell: CellId, axis: usize, sign: bool) -> Option<CellId> {
+        let stride = self.strides[axis];
+        let value = self.coordinate(cell, axis);
+        let edge = if sign { self.dimensions[axis] - 1 } else { 0 };
+        if value != edge {
+            return Some(CellId(if sign { cell.0 + stride } else { cell.0 - stride }));
+        }
+
+        let mut index = cell.0;
+        match self.boundaries[axis] {
+            Boundary::Wrap => {},
+            Boundary::Bounded => return None,
+            Boundary::Twisted(mirror_axis) => {
+                let mirror_value = self.coordinate(cell, mirror_axis);
+                index = index - mirror_value * self.strides[mirror_axis] + (self.dimensions[mirror_axis] - 1 - mirror_value) * self.strides[mirror_axis];
+            },
+        }
+
+        index = index - edge * stride + (self.dimensions[axis] - 1 - edge) * stride;
+        Some(CellId(index))
     }
 
-    /// Get the list of walls neighbouring a cell at position, together with the other cell.
-    pub fn neighbours(&self, position: &[usize]) -> Vec<(Wall, Vec<usize>)> {
-        let mut result = Vec::new();
-        for axis in 0..self.dimensions.len() {
-            for sign in [false, true] {
-                let neighbour_position = self.traverse(position, axis, sign);
-                let wall_position = if sign { position.to_vec() } else { neighbour_position.clone() };
-                result.push((Wall { position: wall_position, axis, }, neighbour_position));
-            }
+    /// Get the wall in the given axis and direction of a cell, together with the other cell, or
+    /// [None] if the cell lies on a bounded edge or the other cell is excluded.
+    pub fn neighbour(&self, cell: CellId, axis: usize, sign: bool) -> Option<(Wall, CellId)> {
+        let neighbour_cell = self.traverse(cell, axis, sign)?;
+        if self.is_excluded(neighbour_cell) {
+            return None;
         }
-        result
+
+        let wall_cell = if sign { cell } else { neighbour_cell };
+        Some((Wall { cell: wall_cell, axis, }, neighbour_cell))
+    }
+
+    /// Iterate over the walls neighbouring a cell, together with the other cell.
+    pub fn neighbours(&self, cell: CellId) -> impl Iterator<Item = (Wall, CellId)> + '_ {
+        (0..self.dimensions.len())
+            .flat_map(move |axis| [false, true].map(|sign| self.neighbour(cell, axis, sign)))
+            .flatten()
+    }
+
+    /// Iterate over the walls of a cell that do not lie on a bounded edge, whether or not the cell
+    /// on the other side is excluded.
+    pub fn cell_walls(&self, cell: CellId) -> impl Iterator<Item = Wall> + '_ {
+        (0..self.dimensions.len())
+            .flat_map(move |axis| [false, true].map(|sign| {
+                let neighbour_cell = self.traverse(cell, axis, sign)?;
+                Some(Wall { cell: if sign { cell } else { neighbour_cell }, axis })
+            }))
+            .flatten()
+    }
+
+    /// Get the two cells on either side of a wall, or [None] if the wall lies on a bounded edge.
+    pub fn wall_cells(&self, wall: &Wall) -> Option<[CellId; 2]> {
+        Some([wall.cell, self.traverse(wall.cell, wall.axis, true)?])
+    }
+
+    /// Pick a random index into a list of neighbours as returned by [Maze::neighbours], weighted by
+    /// the axis weight of each wall, or [None] if the list is empty.
+    pub fn choose_neighbour(&self, neighbours: &[(Wall, CellId)], rng: &mut dyn RngCore) -> Option<usize> {
+        let distribution = WeightedIndex::new(neighbours.iter().map(|(wall, _)| self.axis_weights[wall.axis])).ok()?;
+        Some(distribution.sample(rng))
     }
 
     pub fn reset_walls(&mut self) {
         self.walls.fill(true);
     }
 
+    /// Open every wall between two distinct cells of the maze, which is where generators that put
+    /// walls up rather than knock them down start from. Walls across the edge of a bounded axis
+    /// and walls of excluded cells stay up.
+    pub fn open_walls(&mut self) {
+        self.reset_walls();
+        for index in 0..self.walls.len() {
+            let wall = self.wall_from_index(index);
+            if let Some([cell1, cell2]) = self.wall_cells(&wall)
+                && cell1 != cell2
+                && !self.is_excluded(cell1)
+                && !self.is_excluded(cell2)
+            {
+                self.walls.set(index, false);
+            }
+        }
+    }
+
     pub fn get_wall(&self, wall: &Wall) -> bool {
         let index = self.compute_wall_index(wall);
-        self.walls[index]
+        self.walls.get(index)
     }
 
     pub fn set_wall(&mut self, wall: &Wall, value: bool) {
         let index = self.compute_wall_index(wall);
-        self.walls[index] = value;
+        self.walls.set(index, value);
     }
 
-    pub fn generate<R: Rng + ?Sized>(&mut self, rng: &mut R) {
-        for (limit, value) in std::iter::zip(self.dimensions.iter(), self.start.iter_mut()) {
-            *value = rng.random_range(0..*limit);
+    /// Pick a cell that is not excluded uniformly at random.
+    pub fn random_cell(&self, rng: &mut dyn RngCore) -> CellId {
+        loop {
+            let cell = CellId(rng.random_range(0..self.cell_count()));
+            if !self.is_excluded(cell) {
+                return cell;
+            }
         }
+    }
 
-        for (limit, value) in std::iter::zip(self.dimensions.iter(), self.end.iter_mut()) {
-            *value = rng.random_range(0..*limit);
+    /// Carve out a new maze using the given generator, and pick the start and end according to the
+    /// placement policy.
+    pub fn generate(&mut self, generator: &dyn MazeGenerator, rng: &mut dyn RngCore) {
+        let mut stepper = self.start_generation(generator, rng);
+        while stepper.step(self, rng).is_some() {}
+        self.finish_generation(rng);
+    }
+
+    /// Reset every wall, or open every wall if the generator asks for it, remove every door,
+    /// one-way passage and portal, and start the given generator, which can then be stepped
+    /// through one wall at a time. Once it is done, [Maze::finish_generation] must be called with
+    /// the same random number generator to get the same result as [Maze::generate].
+    pub fn start_generation(&mut self, generator: &dyn MazeGenerator, rng: &mut dyn RngCore) -> Box<dyn GeneratorStepper> {
+        if generator.starts_open() {
+            self.open_walls();
+        } else {
+            self.reset_walls();
         }
+        self.one_way.fill(false);
+        self.doors.clear();
+        self.keys.clear();
+        self.portals.clear();
+        self.generator = generator.describe();
+        generator.start(self, rng)
+    }
 
-        // Yep. This waste a lot of memory, but apparently who cares?
-        let mut visited = HashSet::<Vec<usize>>::from_iter([self.start.clone()]);
-        let mut walls = Wall::from_cell(&self.dimensions, &self.start);
+    /// Join up whatever the generator left over, and pick the start and end according to the
+    /// placement policy.
+    pub fn finish_generation(&mut self, rng: &mut dyn RngCore) {
+        // The mask might split the maze into several pieces, in which case we make sure every piece
+        // is a perfect maze on its own. All placement policies below pick the end among the cells
+        // reachable from the start.
+        if self.mask.is_some() {
+            self.connect_components(rng);
+        }
 
-        self.reset_walls();
-        while !walls.is_empty() {
-            let wall = walls.swap_remove(rng.random_range(0..walls.len()));
-
-            let mut okay = false;
-            for cell in wall.get_neighbour_cells(&self.dimensions) {
-                if !visited.contains(&cell) {
-                    for wall in Wall::from_cell(&self.dimensions, &cell) {
-                        if self.get_wall(&wall) {
-                            walls.push(wall);
-                        }
-                    }
-                    visited.insert(cell);
-                    okay = true;
+        self.start = self.random_cell(rng);
+        match self.placement {
+            Placement::Random => {
+                if self.mask.is_some() {
+                    self.end = self.reachable(self.start).choose(rng).unwrap().0;
+                } else {
+                    self.end = self.random_cell(rng);
                 }
+            },
+            Placement::MinDistance(min_distance) => {
+                let reachable = self.reachable(self.start);
+                let candidates = reachable
+                    .iter()
+                    .filter(|(_, distance)| *distance >= min_distance)
+                    .collect::<Vec<_>>();
+
+                self.end = match candidates.choose(rng) {
+                    Some((cell, _)) => *cell,
+                    None => reachable.last().unwrap().0,
+                };
+            },
+            Placement::Diameter => {
+                // In a tree, the farthest cell from any cell is an end of a longest path, and the
+                // farthest cell from there is the other end. Anything else only gets an
+                // approximation, since finding the farthest pair exactly takes a search from
+                // every cell.
+                self.start = self.reachable(self.start).pop().unwrap().0;
+                self.end = self.reachable(self.start).pop().unwrap().0;
+            },
+        }
+    }
+
+    /// Knock down walls between cells that are not yet connected in random order, until every cell
+    /// is connected to every other cell it could possibly be connected to.
+    pub fn connect_components(&mut self, rng: &mut dyn RngCore) {
+        let mut sets = DisjointSet::new(self.cell_count());
+        let mut candidates = Vec::new();
+        for index in 0..self.walls.len() {
+            let wall = self.wall_from_index(index);
+            let Some([cell1, cell2]) = self.wall_cells(&wall) else {
+                continue;
+            };
+
+            if self.is_excluded(cell1) || self.is_excluded(cell2) {
+                continue;
             }
 
-            if okay {
+            if self.walls.get(index) {
+                candidates.push((wall, cell1, cell2));
+            } else {
+                sets.union(cell1.0, cell2.0);
+            }
+        }
+
+        candidates.shuffle(rng);
+        for (wall, cell1, cell2) in candidates {
+            if sets.union(cell1.0, cell2.0) {
                 self.set_wall(&wall, false);
             }
         }
     }
 
-    /// Compute the taxicab distance between two positions but take into account the fact that we
-    /// are on a torus.
-    fn distance(&self, position1: &[usize], position2: &[usize]) -> usize {
-        let mut result : usize = 0;
-        for (i, dimension) in self.dimensions.iter().enumerate() {
-            result += (position1[i] as isize - position2[i] as isize).div_euclid(*dimension as isize) as usize;
+    /// Get the list of cells that can be reached from a cell together with their distance, in
+    /// breadth first order, so that the farthest cell comes last. Doors are treated as open, but
+    /// one-way passages are honoured, and stepping onto a portal moves on to the other end.
+    pub fn reachable(&self, cell: CellId) -> Vec<(CellId, usize)> {
+        self.reachable_with_keys(cell, u32::MAX)
+    }
+
+    /// Same as [Maze::reachable], but only going through the doors whose key is in the bitmask of
+    /// keys held.
+    pub fn reachable_with_keys(&self, cell: CellId, keys: u32) -> Vec<(CellId, usize)> {
+        let mut visited = vec![false; self.cell_count()];
+        visited[cell.0] = true;
+
+        let mut result = vec![(cell, 0)];
+        let mut index = 0;
+        while let Some(&(cell, distance)) = result.get(index) {
+            for (wall, neighbour_cell) in self.neighbours(cell) {
+                let neighbour_cell = self.portal(neighbour_cell).unwrap_or(neighbour_cell);
+                if self.can_pass(cell, &wall, keys) && !visited[neighbour_cell.0] {
+                    visited[neighbour_cell.0] = true;
+                    result.push((neighbour_cell, distance + 1));
+                }
+            }
+            index += 1;
         }
         result
     }
 
-    pub fn solve(&mut self) -> Vec<Vec<usize>> {
-        #[derive(Debug)]
-        struct Node {
-            g_score: usize,
-            f_score: usize,
-            position: Vec<usize>,
+    /// Get the cells from which a cell can be reached, only going through the doors whose key is in
+    /// the bitmask of keys held. This is not the same as the cells that can be reached from it, as
+    /// one-way passages and portals can only be gone through one way.
+    fn reaching_with_keys(&self, cell: CellId, keys: u32) -> BitSet {
+        let mut result = BitSet::new(self.cell_count(), false);
+        result.set(cell.0, true);
+
+        let mut queue = vec![cell];
+        while let Some(cell) = queue.pop() {
+            // A cell with a portal is only ever landed on by stepping onto the other end.
+            let entry_cell = self.portal(cell).unwrap_or(cell);
+            for (wall, neighbour_cell) in self.neighbours(entry_cell) {
+                if self.can_pass(neighbour_cell, &wall, keys) && !result.get(neighbour_cell.0) {
+                    result.set(neighbour_cell.0, true);
+                    queue.push(neighbour_cell);
+                }
+            }
         }
+        result
+    }
 
-        impl BinaryHashHeapItem for Node {
-            type Key = Vec<usize>;
-            type Value = usize;
+    /// Get the number of steps from a cell to every cell over open walls, or [None] for cells that
+    /// cannot be reached. Unlike [Maze::reachable], this goes through one-way passages either way,
+    /// so that it gives the distance to the cell as well as from it, and ignores portals.
+    pub fn distance_field(&self, cell: CellId) -> Vec<Option<usize>> {
+        let mut result = vec![None; self.cell_count()];
+        result[cell.0] = Some(0);
 
-            fn key(&self) -> &Self::Key {
-                &self.position
+        let mut queue = vec![cell];
+        let mut index = 0;
+        while let Some(&cell) = queue.get(index) {
+            let distance = result[cell.0].unwrap();
+            for (wall, neighbour_cell) in self.neighbours(cell) {
+                if !self.get_wall(&wall) && result[neighbour_cell.0].is_none() {
+                    result[neighbour_cell.0] = Some(distance + 1);
+                    queue.push(neighbour_cell);
+                }
             }
+            index += 1;
+        }
+        result
+    }
+
+    /// Count the open walls of a cell.
+    pub fn degree(&self, cell: CellId) -> usize {
+        self.neighbours(cell).filter(|(wall, _)| !self.get_wall(wall)).count()
+    }
+
+    /// Check if a cell is a dead end, i.e. there is exactly one way out of it.
+    pub fn is_dead_end(&self, cell: CellId) -> bool {
+        self.degree(cell) == 1
+    }
 
-            fn value(&self) -> &Self::Value {
-                &self.f_score
+    /// Knock down a wall in each dead end with probability fraction, preferring walls that lead
+    /// into another dead end, so that the maze is no longer perfect and contains loops.
+    pub fn braid(&mut self, fraction: f64, rng: &mut dyn RngCore) {
+        let mut dead_ends = self.cells()
+            .filter(|cell| self.is_dead_end(*cell))
+            .collect::<Vec<_>>();
+
+        dead_ends.shuffle(rng);
+        for cell in dead_ends {
+            if !rng.random_bool(fraction) {
+                continue;
             }
-        }
 
-        let mut open = BinaryHashHeap::default();
-        open.push(PushAction::Keep, Node {
-            position: self.start.clone(),
-            g_score: 0,
-            f_score: self.distance(&self.start, &self.end)
-        });
+            // Might no longer be a dead end if we already knocked down a wall leading into it.
+            if !self.is_dead_end(cell) {
+                continue;
+            }
 
-        let mut visited = HashSet::new();
-        let mut links = HashMap::new();
+            let candidates = self
+                .neighbours(cell)
+                .filter(|(wall, neighbour_cell)| *neighbour_cell != cell && self.get_wall(wall))
+                .collect::<Vec<_>>();
+
+            let preferred_candidates = candidates
+                .iter()
+                .filter(|(_, neighbour_cell)| self.is_dead_end(*neighbour_cell))
+                .collect::<Vec<_>>();
+
+            let wall = match preferred_candidates.choose(rng) {
+                Some((wall, _)) => *wall,
+                None => match candidates.choose(rng) {
+                    Some((wall, _)) => *wall,
+                    None => continue,
+                },
+            };
 
-        while let Some(node) = open.pop() {
-            if node.position == self.end {
-                let mut paths = Vec::new();
+            self.set_wall(&wall, false);
+        }
+    }
 
-                let mut current = self.end.clone();
-                while current != self.start {
-                    let next = links.remove(&current).unwrap();
-                    paths.push(current);
-                    current = next;
-                }
+    /// Compute the taxicab distance between two cells but take into account the boundary of each
+    /// axis, so that this never overestimates the length of a path between them that does not go
+    /// through a portal.
+    fn distance(&self, cell1: CellId, cell2: CellId) -> usize {
+        let axis_distance = |axis: usize, value1: usize, value2: usize| {
+            let distance = value1.abs_diff(value2);
+            match self.boundaries[axis] {
+                Boundary::Wrap | Boundary::Twisted(_) => distance.min(self.dimensions[axis] - distance),
+                Boundary::Bounded => distance,
+            }
+        };
 
-                paths.push(current);
-                paths.reverse();
-                return paths;
+        let mut result : usize = 0;
+        for axis in 0..self.dimensions.len() {
+            let value1 = self.coordinate(cell1, axis);
+            let value2 = self.coordinate(cell2, axis);
+            let mut distance = axis_distance(axis, value1, value2);
+
+            // Crossing a twisted edge mirrors this axis, so we could also arrive mirrored.
+            if self.boundaries.contains(&Boundary::Twisted(axis)) {
+                let mirrored_value1 = self.dimensions[axis] - 1 - value1;
+                distance = distance.min(axis_distance(axis, mirrored_value1, value2));
             }
 
-            for (wall, neighbour_position) in self.neighbours(&node.position) {
-                if visited.contains(&neighbour_position) {
-                    continue;
-                }
+            result += distance;
+        }
+        result
+    }
 
-                if self.get_wall(&wall) {
-                    continue;
-                }
+    /// Same as [Maze::distance], but taking into account that the route could go through a portal
+    /// instead, so that this still never overestimates the length of a path between them.
+    ///
+    /// Going through a portal takes at least as many steps as it takes to get to any portal, and
+    /// then on from wherever a portal comes out.
+    fn portal_distance(&self, cell1: CellId, cell2: CellId) -> usize {
+        let result = self.distance(cell1, cell2);
+        if self.portals.is_empty() {
+            return result;
+        }
 
-                let g_score = node.g_score + 1;
-                let f_score = g_score + self.distance(&neighbour_position, &self.end);
-                if !open.push(PushAction::DecreaseKey, Node {
-                    position: neighbour_position.clone(),
-                    g_score, f_score,
-                }) {
-                    continue;
-                }
+        let ends = || self.portals.iter().flatten().copied();
+        let entry = ends().map(|cell| self.distance(cell1, cell)).min().unwrap();
+        let exit = ends().map(|cell| self.distance(cell, cell2)).min().unwrap();
+        result.min(entry + exit)
+    }
+
+    /// Get the other end of the portal in a cell, if there is one.
+    pub fn portal(&self, cell: CellId) -> Option<CellId> {
+        self.portals.iter().find_map(|[cell1, cell2]| {
+            if *cell1 == cell {
+                Some(*cell2)
+            } else if *cell2 == cell {
+                Some(*cell1)
+            } else {
+                None
+            }
+        })
+    }
 
-                links.insert(neighbour_position, node.position.clone());
+    /// Place up to count portals between random pairs of cells reachable from the start, other
+    /// than the start, the end, checkpoints and keys. A pair is only kept if the end can still be
+    /// reached afterwards, since stepping onto a portal always moves on to the other end, so it
+    /// can cut off a passage behind it.
+    pub fn place_portals(&mut self, count: usize, rng: &mut dyn RngCore) {
+        self.portals.clear();
+
+        let mut candidates = self.reachable(self.start)
+            .into_iter()
+            .map(|(cell, _)| cell)
+            .filter(|cell| *cell != self.start && *cell != self.end && !self.checkpoints.contains(cell) && !self.keys.contains(cell))
+            .collect::<Vec<_>>();
+
+        candidates.shuffle(rng);
+        for pair in candidates.chunks_exact(2).take(count * 4) {
+            if self.portals.len() == count {
+                break;
             }
 
-            visited.insert(node.position);
+            self.portals.push([pair[0], pair[1]]);
+            if AStar.solve(self, Progress::start(self)).is_none() {
+                self.portals.pop();
+            }
         }
+    }
 
-        panic!("No path found")
+    /// Place count checkpoints on distinct cells reachable from the start, other than the start and
+    /// the end. Fewer checkpoints are placed if the maze is too small.
+    pub fn place_checkpoints(&mut self, count: usize, rng: &mut dyn RngCore) {
+        let candidates = self.reachable(self.start)
+            .into_iter()
+            .map(|(cell, _)| cell)
+            .filter(|cell| *cell != self.start && *cell != self.end)
+            .collect::<Vec<_>>();
+
+        // The sample is not in random order, which would make the route rather predictable.
+        self.checkpoints = candidates.choose_multiple(rng, count).copied().collect();
+        self.checkpoints.shuffle(rng);
+        self.checkpoints_reached = 0;
     }
 
-    pub fn start(&mut self) {
-        self.position.copy_from_slice(&self.start);
+    /// Check if a wall of a cell can be passed through from that cell while holding the keys in
+    /// the bitmask, i.e. it is open, not a one-way passage in the other direction, and either not a
+    /// door or a door whose key is held.
+    pub fn can_pass(&self, cell: CellId, wall: &Wall, keys: u32) -> bool {
+        let index = self.compute_wall_index(wall);
+        if self.walls.get(index) {
+            return false;
+        }
+
+        // A wall is in the positive direction of the cell it belongs to.
+        if self.one_way.get(index) && self.one_way_positive.get(index) != (wall.cell == cell) {
+            return false;
+        }
+
+        self.doors.get(wall).is_none_or(|colour| keys & (1 << colour) != 0)
     }
 
-    pub fn walk(&mut self, view_axis: usize, sign: bool) {
-        if sign {
-            if self.get_wall(&Wall { position: self.position.clone(), axis: self.axes[view_axis] }) {
-                return;
-            }
+    /// Find the wall a step of a route from a cell to a neighbouring cell goes through, or to the
+    /// other end of the portal in a neighbouring cell.
+    fn step_wall(&self, cell: CellId, neighbour_cell: CellId) -> Wall {
+        self.neighbours(cell)
+            .find(|(wall, other_cell)| self.portal(*other_cell).unwrap_or(*other_cell) == neighbour_cell && self.can_pass(cell, wall, u32::MAX))
+            .unwrap()
+            .0
+    }
 
-            if self.position[self.axes[view_axis]] != self.dimensions[self.axes[view_axis]] - 1 {
-                self.position[self.axes[view_axis]] += 1;
-            } else {
-                self.position[self.axes[view_axis]] = 0;
+    /// Add the colours of the keys lying in a cell to the bitmask of keys held.
+    fn collect_keys(&self, cell: CellId, keys: u32) -> u32 {
+        self.keys
+            .iter()
+            .enumerate()
+            .filter(|(_, key)| **key == cell)
+            .fold(keys, |keys, (colour, _)| keys | 1 << colour)
+    }
+
+    /// Lock count distinct passages along the solution behind doors of different colours, and
+    /// place the key of each door in a cell that can be reached from the start with only the keys
+    /// of the doors before it, and from which the start can be reached again with the same keys,
+    /// so that the end can always be reached. Keys are kept off the start, the end, checkpoints,
+    /// portals and each other, so that every key can be seen. Fewer doors are placed if the
+    /// solution is too short or there is no room left for their keys, and none if there is no
+    /// solution.
+    pub fn place_doors(&mut self, count: usize, rng: &mut dyn RngCore) {
+        assert!(count <= u32::BITS as usize);
+        self.doors.clear();
+        self.keys.clear();
+
+        let solution = self.solve().unwrap_or_default();
+        let mut seen = HashSet::new();
+        let mut walls = Vec::new();
+        for step in solution.windows(2) {
+            let wall = self.step_wall(step[0], step[1]);
+            if seen.insert(wall) {
+                walls.push(wall);
             }
-        } else {
-            let old_value = self.position[self.axes[view_axis]];
+        }
 
-            if self.position[self.axes[view_axis]] != 0 {
-                self.position[self.axes[view_axis]] -= 1;
-            } else {
-                self.position[self.axes[view_axis]] = self.dimensions[self.axes[view_axis]] - 1;
+        // Colours follow the order of the doors along the solution, so that the key of a door is
+        // never locked behind the door itself or any door after it.
+        let mut indices = rand::seq::index::sample(rng, walls.len(), count.min(walls.len())).into_vec();
+        indices.sort();
+        for (colour, index) in indices.into_iter().enumerate() {
+            self.doors.insert(walls[index], colour);
+        }
+
+        for colour in 0..self.doors.len() {
+            let keys = (1 << colour) - 1;
+            let returning = self.reaching_with_keys(self.start, keys);
+            let candidates = self.reachable_with_keys(self.start, keys)
+                .into_iter()
+                .map(|(cell, _)| cell)
+                .filter(|cell| returning.get(cell.0) && self.portal(*cell).is_none())
+                .filter(|cell| *cell != self.start && *cell != self.end && !self.checkpoints.contains(cell) && !self.keys.contains(cell))
+                .collect::<Vec<_>>();
+
+            let Some(cell) = candidates.choose(rng) else {
+                self.doors.retain(|_, other_colour| *other_colour < colour);
+                break;
+            };
+            self.keys.push(*cell);
+        }
+
+        self.keys_held = self.collect_keys(self.start, 0);
+    }
+
+    /// Turn each open passage other than a door into a one-way passage with probability fraction,
+    /// in a random direction. Passages the solution goes through are only ever turned into one-way
+    /// passages in the direction it goes through them, so that the end stays reachable from the
+    /// start.
+    pub fn make_one_way(&mut self, fraction: f64, rng: &mut dyn RngCore) {
+        self.one_way.fill(false);
+
+        // Direction the solution goes through each passage, or None if it goes through both ways.
+        let mut directions = HashMap::new();
+        for step in self.solve().unwrap_or_default().windows(2) {
+            let wall = self.step_wall(step[0], step[1]);
+            let direction = Some(wall.cell == step[0]);
+            directions
+                .entry(wall)
+                .and_modify(|other_direction| if *other_direction != direction { *other_direction = None })
+                .or_insert(direction);
+        }
+
+        for index in 0..self.walls.len() {
+            let wall = self.wall_from_index(index);
+            let Some([cell1, cell2]) = self.wall_cells(&wall) else {
+                continue;
+            };
+
+            if self.walls.get(index) || cell1 == cell2 || self.is_excluded(cell1) || self.is_excluded(cell2) || self.doors.contains_key(&wall) {
+                continue;
             }
 
-            if self.get_wall(&Wall { position: self.position.clone(), axis: self.axes[view_axis] }) {
-                self.position[self.axes[view_axis]] = old_value;
+            if !rng.random_bool(fraction) {
+                continue;
             }
+
+            let positive = match directions.get(&wall) {
+                Some(Some(positive)) => *positive,
+                Some(None) => continue,
+                None => rng.random(),
+            };
+
+            self.one_way.set(index, true);
+            self.one_way_positive.set(index, positive);
+        }
+    }
+
+    /// Get the direction a wall can be passed through in if it is a one-way passage, true for the
+    /// positive direction along its axis.
+    pub fn one_way_direction(&self, wall: &Wall) -> Option<bool> {
+        let index = self.compute_wall_index(wall);
+        self.one_way.get(index).then(|| self.one_way_positive.get(index))
+    }
+
+    /// Check if every checkpoint has been visited and we are at the end.
+    pub fn is_finished(&self) -> bool {
+        self.checkpoints_reached == self.checkpoints.len() && self.position == self.end
+    }
+
+    /// Find the shortest route from the start through every checkpoint in order to the end,
+    /// picking up keys to get through doors on the way, using A*.
+    ///
+    /// The route may pass through the same cell more than once. Stepping onto a portal moves
+    /// straight on to the other end, so a route can jump between cells that are not neighbours.
+    ///
+    /// Return why there is no such route if there is none, which can happen once one-way passages
+    /// or masked out cells cut the maze apart.
+    pub fn solve(&self) -> Result<Vec<CellId>, NoPath> {
+        self.solve_with(&AStar)
+    }
+
+    /// Same as [Maze::solve], but using the given solver.
+    pub fn solve_with(&self, solver: &dyn Solver) -> Result<Vec<CellId>, NoPath> {
+        let from = Progress::start(self);
+        solver.solve(self, from).ok_or_else(|| NoPath::explain(self, from))
+    }
+
+    /// Find the shortest route on from the current position to the end, with the keys held and
+    /// checkpoints reached so far, using the given solver.
+    pub fn solve_from_position(&self, solver: &dyn Solver) -> Result<Vec<CellId>, NoPath> {
+        let from = Progress::current(self);
+        solver.solve(self, from).ok_or_else(|| NoPath::explain(self, from))
+    }
+
+    pub fn start(&mut self) {
+        self.position = self.start;
+        self.checkpoints_reached = 0;
+        self.keys_held = self.collect_keys(self.start, 0);
+    }
+
+    pub fn walk(&mut self, view_axis: usize, sign: bool) {
+        let Some((wall, neighbour_cell)) = self.neighbour(self.position, self.axes[view_axis], sign) else {
+            return;
+        };
+
+        if !self.can_pass(self.position, &wall, self.keys_held) {
+            return;
+        }
+
+        self.position = self.portal(neighbour_cell).unwrap_or(neighbour_cell);
+        self.keys_held = self.collect_keys(self.position, self.keys_held);
+        if self.checkpoints.get(self.checkpoints_reached) == Some(&self.position) {
+            self.checkpoints_reached += 1;
         }
     }
 
@@ -311,30 +1015,332 @@ impl Maze {
     }
 }
 
+/// Shapes selectable from the setup screen, which are turned into a mask of the maze.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    Box,
+    Sphere,
+    Annulus,
+    Cross,
+}
+
+impl Shape {
+    const ALL: [Shape; 4] = [
+        Shape::Box,
+        Shape::Sphere,
+        Shape::Annulus,
+        Shape::Cross,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Shape::Box => "Box",
+            Shape::Sphere => "Sphere",
+            Shape::Annulus => "Annulus",
+            Shape::Cross => "Cross",
+        }
+    }
+
+    fn next(self) -> Shape {
+        let index = Self::ALL.iter().position(|shape| *shape == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> Shape {
+        let index = Self::ALL.iter().position(|shape| *shape == self).unwrap();
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Check if a cell is part of the shape, after scaling the maze to fit in [-1, 1] along every
+    /// axis.
+    fn contains(self, dimensions: &[usize], position: &[usize]) -> bool {
+        let coordinates = std::iter::zip(dimensions.iter(), position.iter())
+            .map(|(limit, value)| (2 * *value + 1) as f64 / *limit as f64 - 1.0);
+
+        match self {
+            Shape::Box => true,
+            Shape::Sphere => coordinates.map(|coordinate| coordinate * coordinate).sum::<f64>() <= 1.0,
+            Shape::Annulus => (0.25..=1.0).contains(&coordinates.map(|coordinate| coordinate * coordinate).sum::<f64>()),
+            Shape::Cross => coordinates.filter(|coordinate| coordinate.abs() > 1.0 / 3.0).count() <= 1,
+        }
+    }
+}
+
+/// Field of the setup screen currently being edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuField {
+    Dimension,
+    Shape,
+    Boundaries,
+    Generator,
+    Policy,
+    RoomSize,
+    AxisWeights,
+    Placement,
+    Solver,
+    Hints,
+    Checkpoints,
+    Doors,
+    OneWay,
+    Portals,
+    Braid,
+    Animate,
+    Seed,
+    MemoryBudget,
+}
+
+impl MenuField {
+    const ALL: [MenuField; 18] = [
+        MenuField::Dimension,
+        MenuField::Shape,
+        MenuField::Boundaries,
+        MenuField::Generator,
+        MenuField::Policy,
+        MenuField::RoomSize,
+        MenuField::AxisWeights,
+        MenuField::Placement,
+        MenuField::Solver,
+        MenuField::Hints,
+        MenuField::Checkpoints,
+        MenuField::Doors,
+        MenuField::OneWay,
+        MenuField::Portals,
+        MenuField::Braid,
+        MenuField::Animate,
+        MenuField::Seed,
+        MenuField::MemoryBudget,
+    ];
+
+    fn next(self) -> MenuField {
+        let index = Self::ALL.iter().position(|field| *field == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn prev(self) -> MenuField {
+        let index = Self::ALL.iter().position(|field| *field == self).unwrap();
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+/// Cell that the heat map measures distances from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HeatSource {
+    End,
+    Player,
+}
+
+/// Distance of every cell from an origin, as returned by [Maze::distance_field], kept around so
+/// that it is not worked out again every frame.
+struct DistanceField {
+    origin: CellId,
+    distances: Vec<Option<usize>>,
+    /// Largest distance of a cell that can be reached, which the gradient is stretched over.
+    max_distance: usize,
+}
+
+impl DistanceField {
+    fn new(maze: &Maze, origin: CellId) -> Self {
+        let distances = maze.distance_field(origin);
+        let max_distance = distances.iter().flatten().copied().max().unwrap_or(0);
+        Self { origin, distances, max_distance }
+    }
+
+    /// Colour of a distance, going from red next to the origin to blue farthest away.
+    fn colour(&self, distance: usize) -> Color {
+        let t = distance as f64 / self.max_distance.max(1) as f64;
+        Color::Rgb((200.0 * (1.0 - t)) as u8, 40, (200.0 * t) as u8)
+    }
+}
+
+/// Search for the solution shown one state at a time on the main screen, which stays on screen
+/// once it is over until hidden.
+struct SearchAnimation {
+    stepper: Box<dyn SolverStepper>,
+    /// Number of states expanded per frame.
+    speed: usize,
+    paused: bool,
+    done: bool,
+}
+
 enum Application {
     Menu {
+        field: MenuField,
         dimension: String,
+        shape: Shape,
+        boundaries: String,
+        generator: GeneratorKind,
+        policy: String,
+        /// Longest side of the rooms left behind by recursive division.
+        room_size: u32,
+        axis_weights: String,
+        placement: String,
+        solver: SolverKind,
+        /// Number of hints allowed per game.
+        hints: u32,
+        /// Number of checkpoints to visit in order before the end.
+        checkpoints: u32,
+        /// Number of locked doors, each with a key of its own colour.
+        doors: u32,
+        /// Percentage of passages to turn into one-way passages.
+        one_way: u32,
+        /// Number of pairs of cells to join with portals.
+        portals: u32,
+        /// Percentage of dead ends to remove.
+        braid: u32,
+        /// Whether to show the maze being carved out, rather than skipping straight to the end.
+        animate: bool,
+        seed: String,
+        memory_budget: String,
+    },
+    Generating {
+        maze: Maze,
+        seed: u64,
+        rng: Box<StdRng>,
+        stepper: Box<dyn GeneratorStepper>,
+        /// Number of walls knocked down so far.
+        carved: usize,
+        /// Number of walls knocked down per frame.
+        speed: usize,
+        paused: bool,
+        /// Settings from the setup screen to apply once the generator is done.
+        solver: SolverKind,
+        hints: u32,
+        braid: u32,
+        checkpoints: u32,
+        doors: u32,
+        one_way: u32,
+        portals: u32,
     },
     Main {
         maze: Maze,
+        /// Seed of the random number generator used to generate the maze.
+        seed: u64,
+        /// Solver used when asked to solve the maze.
+        solver: SolverKind,
         view_axis : Option<usize>,
-        solution: Option<Vec<Vec<usize>>>,
+        /// Solution, or why there is none, shown while present.
+        solution: Option<Result<Vec<CellId>, NoPath>>,
+        /// Route on from the current position, shown while there is no solution, or why there is
+        /// none.
+        hint: Option<Result<Vec<CellId>, NoPath>>,
+        hints_used: u32,
+        /// Number of hints allowed in this game.
+        hint_limit: u32,
+        /// Search of the solver being shown step by step.
+        search: Option<Box<SearchAnimation>>,
+        /// Which distances the heat map shows, if it is shown at all.
+        heat_map: Option<HeatSource>,
+        /// Distances from the end, worked out the first time they are shown.
+        end_distances: Option<Box<DistanceField>>,
+        /// Distances from the player, worked out again whenever they are shown after a move.
+        player_distances: Option<Box<DistanceField>>,
+        /// Analysis of the maze, shown in place of the maze while present.
+        stats: Option<Box<MazeStats>>,
     },
 }
 
-fn render_maze(area: Rect, buf: &mut Buffer, maze: &Maze, solution: Option<&Vec<Vec<usize>>>) {
+/// Cell shown in a square on screen, together with the directions along the two view axes that go
+/// down and right on screen from there, true for the positive direction.
+type ViewSquare = (CellId, [bool; 2]);
+
+/// Walk from origin along a view axis one square at a time, and collect the position reached at
+/// every offset from min to max, or [None] if we walked off a bounded edge along the way. Walking
+/// over a twisted edge that mirrors the other view axis flips its direction, since the squares
+/// beyond the edge are laid out the other way round.
+fn walk_line(maze: &Maze, origin: ViewSquare, view_axis: usize, min: isize, max: isize) -> Vec<Option<ViewSquare>> {
+    let axis = maze.axes[view_axis];
+    let other_view_axis = 1 - view_axis;
+    let mut result = vec![None; (max - min + 1).max(0) as usize];
+    for forward in [false, true] {
+        let mut position = Some(origin);
+        let mut offset = 0;
+        while min <= offset && offset <= max {
+            result[(offset - min) as usize] = position;
+            position = position.and_then(|(cell, mut signs)| {
+                let sign = signs[view_axis] == forward;
+                let edge = if sign { maze.dimensions[axis] - 1 } else { 0 };
+                if maze.coordinate(cell, axis) == edge && maze.boundaries[axis] == Boundary::Twisted(maze.axes[other_view_axis]) {
+                    signs[other_view_axis] = !signs[other_view_axis];
+                }
+                Some((maze.traverse(cell, axis, sign)?, signs))
+            });
+            offset += if forward { 1 } else { -1 };
+        }
+    }
+    result
+}
+
+/// Lay out the squares around the current position, from min_y to max_y squares down and min_x to
+/// max_x squares right, by walking down from the current position and then right from every
+/// position reached, so that bounded and twisted edges are taken into account.
+fn view_cells(maze: &Maze, min_y: isize, max_y: isize, min_x: isize, max_x: isize) -> Vec<Vec<Option<ViewSquare>>> {
+    walk_line(maze, (maze.position, [true, true]), 0, min_y, max_y)
+        .into_iter()
+        .map(|position| match position {
+            Some(position) => walk_line(maze, position, 1, min_x, max_x),
+            None => vec![None; (max_x - min_x + 1).max(0) as usize],
+        })
+        .collect()
+}
+
+/// Name and colour of the doors and keys of each colour, which also limits how many doors can be
+/// placed from the setup screen.
+const KEY_COLOURS: [(&str, Color); 6] = [
+    ("Blue", Color::Blue),
+    ("Red", Color::LightRed),
+    ("Green", Color::LightGreen),
+    ("Yellow", Color::LightYellow),
+    ("Cyan", Color::LightCyan),
+    ("Magenta", Color::LightMagenta),
+];
+
+/// Draw the part of the maze around the current position. The start, end and checkpoints are only
+/// marked if show_markers is set, since they are not known while the maze is being generated. The
+/// cells a search has expanded or is yet to expand are shaded wherever nothing else is drawn, and
+/// the heat map colours the background of everything else that is not a wall.
+fn render_maze(area: Rect, buf: &mut Buffer, maze: &Maze, solution: Option<&Vec<CellId>>, search: Option<&dyn SolverStepper>, heat_map: Option<&DistanceField>, show_markers: bool) {
     let height = area.height;
     let width = area.width / 2;
 
+    let min_y = (-((height / 2) as isize)).div_euclid(2);
+    let max_y = ((height - height / 2) as isize - 1).div_euclid(2);
+    let min_x = (-((width / 2) as isize)).div_euclid(2);
+    let max_x = ((width - width / 2) as isize - 1).div_euclid(2);
+    let cells = view_cells(maze, min_y, max_y, min_x, max_x);
+
+    let checkpoints = maze.checkpoints
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| (*cell, ((i + 1) % 100) as u8))
+        .collect::<HashMap<_, _>>();
+
+    let keys = maze.keys
+        .iter()
+        .enumerate()
+        .filter(|(colour, _)| maze.keys_held & (1 << colour) == 0)
+        .map(|(colour, cell)| (*cell, colour))
+        .collect::<HashMap<_, _>>();
+
+    let portals = maze.portals
+        .iter()
+        .enumerate()
+        .flat_map(|(i, cells)| cells.map(|cell| (cell, i)))
+        .collect::<HashMap<_, _>>();
+
     let solution = solution
         .iter()
         .copied()
         .flatten()
-        .cloned()
+        .copied()
         .enumerate()
-        .map(|(i, p)| (p, (i % 100) as u8) )
+        .map(|(i, cell)| (cell, (i % 100) as u8) )
         .collect::<HashMap<_, _>>();
 
+    let open = search
+        .map(|search| search.open().into_iter().collect::<HashSet<_>>())
+        .unwrap_or_default();
+    let trace = search.map(|search| search.trace());
+
     for y in 0..height {
         for x in 0..width {
             let wy = y as isize - (height / 2) as isize;
@@ -346,33 +1352,66 @@ fn render_maze(area: Rect, buf: &mut Buffer, maze: &Maze, solution: Option<&Vec<
                 Start,
                 End,
                 Current,
+                Checkpoint(u8, bool),
+                Key(usize),
+                Portal(usize),
+                Door(usize, bool),
+                OneWay(char),
                 Solution(u8),
+                Frontier,
+                Open,
+                Visited,
             }
 
             match match (wy.rem_euclid(2), wx.rem_euclid(2)) {
                 (1, 1) => RenderCell::Wall,
-                (ry, rx)  => {
-                    let mut position = maze.position.clone();
-                    position[maze.axes[0]] = (position[maze.axes[0]] as isize + wy.div_euclid(2)).rem_euclid(maze.dimensions[maze.axes[0]] as isize) as usize;
-                    position[maze.axes[1]] = (position[maze.axes[1]] as isize + wx.div_euclid(2)).rem_euclid(maze.dimensions[maze.axes[1]] as isize) as usize;
-                    match (ry, rx) {
+                (ry, rx)  => match cells[(wy.div_euclid(2) - min_y) as usize][(wx.div_euclid(2) - min_x) as usize] {
+                    None => RenderCell::Wall,
+                    Some((cell, _)) if maze.is_excluded(cell) => RenderCell::Wall,
+                    Some((cell, signs)) => match (ry, rx) {
                         (0, 0) => {
-                            if position == maze.start {
+                            if show_markers && cell == maze.start {
                                 RenderCell::Start
-                            } else if position == maze.end {
+                            } else if show_markers && cell == maze.end {
                                 RenderCell::End
-                            } else if position == maze.position {
+                            } else if cell == maze.position {
                                 RenderCell::Current
-                            } else if let Some(i) = solution.get(&position) {
+                            } else if let Some(i) = checkpoints.get(&cell) {
+                                RenderCell::Checkpoint(*i, (*i as usize) <= maze.checkpoints_reached)
+                            } else if let Some(colour) = keys.get(&cell) {
+                                RenderCell::Key(*colour)
+                            } else if show_markers && let Some(i) = portals.get(&cell) {
+                                RenderCell::Portal(*i)
+                            } else if let Some(i) = solution.get(&cell) {
                                 RenderCell::Solution(*i)
+                            } else if trace.is_some_and(|trace| trace.current == Some(cell)) {
+                                RenderCell::Frontier
+                            } else if open.contains(&cell) {
+                                RenderCell::Open
+                            } else if trace.is_some_and(|trace| trace.visited.get(cell.0)) {
+                                RenderCell::Visited
                             } else {
                                 RenderCell::Empty
                             }
                         },
-                        (1, 0) => if maze.get_wall(&Wall { position, axis: maze.axes[0] }) { RenderCell::Wall } else { RenderCell::Empty },
-                        (0, 1) => if maze.get_wall(&Wall { position, axis: maze.axes[1] }) { RenderCell::Wall } else { RenderCell::Empty },
+                        (1, 0) => match maze.neighbour(cell, maze.axes[0], signs[0]) {
+                            Some((wall, _)) if !maze.get_wall(&wall) => match (maze.doors.get(&wall), maze.one_way_direction(&wall)) {
+                                (Some(colour), _) => RenderCell::Door(*colour, maze.keys_held & (1 << colour) != 0),
+                                (None, Some(positive)) => RenderCell::OneWay(if positive == signs[0] { '↓' } else { '↑' }),
+                                (None, None) => RenderCell::Empty,
+                            },
+                            _ => RenderCell::Wall,
+                        },
+                        (0, 1) => match maze.neighbour(cell, maze.axes[1], signs[1]) {
+                            Some((wall, _)) if !maze.get_wall(&wall) => match (maze.doors.get(&wall), maze.one_way_direction(&wall)) {
+                                (Some(colour), _) => RenderCell::Door(*colour, maze.keys_held & (1 << colour) != 0),
+                                (None, Some(positive)) => RenderCell::OneWay(if positive == signs[1] { '→' } else { '←' }),
+                                (None, None) => RenderCell::Empty,
+                            },
+                            _ => RenderCell::Wall,
+                        },
                         _ => unreachable!(),
-                    }
+                    },
                 },
             } {
                 RenderCell::Wall => {
@@ -395,10 +1434,77 @@ fn render_maze(area: Rect, buf: &mut Buffer, maze: &Maze, solution: Option<&Vec<
                     buf[Position { x: area.x + x * 2 + 0, y : area.y + y }].set_char('█').set_fg(Color::Yellow);
                     buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char('█').set_fg(Color::Yellow);
                 },
+                RenderCell::Checkpoint(i, reached) => {
+                    let color = if reached { Color::DarkGray } else { Color::Magenta };
+                    buf[Position { x: area.x + x * 2, y : area.y + y }].set_char(char::from_digit((i / 10) as u32, 10).unwrap()).set_fg(color);
+                    buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char(char::from_digit((i % 10) as u32, 10).unwrap()).set_fg(color);
+                },
+                RenderCell::Key(colour) => {
+                    let color = KEY_COLOURS[colour % KEY_COLOURS.len()].1;
+                    buf[Position { x: area.x + x * 2, y : area.y + y }].set_char('o').set_fg(color);
+                    buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char('-').set_fg(color);
+                },
+                RenderCell::Portal(i) => {
+                    let color = KEY_COLOURS[i % KEY_COLOURS.len()].1;
+                    buf[Position { x: area.x + x * 2, y : area.y + y }].set_char('@').set_fg(color);
+                    buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char(char::from_digit((i + 1) as u32 % 10, 10).unwrap()).set_fg(color);
+                },
+                RenderCell::Door(colour, unlocked) => {
+                    let color = KEY_COLOURS[colour % KEY_COLOURS.len()].1;
+                    let c = if unlocked { '░' } else { '▒' };
+                    buf[Position { x: area.x + x * 2, y : area.y + y }].set_char(c).set_fg(color);
+                    buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char(c).set_fg(color);
+                },
+                RenderCell::OneWay(arrow) => {
+                    buf[Position { x: area.x + x * 2, y : area.y + y }].set_char(arrow).set_fg(Color::White);
+                    buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char(' ');
+                },
                 RenderCell::Solution(i) => {
                     buf[Position { x: area.x + x * 2 + 0, y : area.y + y }].set_char(char::from_digit((i / 10) as u32, 10).unwrap()).set_fg(Color::Cyan);
                     buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char(char::from_digit((i % 10) as u32, 10).unwrap()).set_fg(Color::Cyan);
                 },
+                RenderCell::Frontier => {
+                    buf[Position { x: area.x + x * 2, y : area.y + y }].set_char(' ').set_bg(Color::LightRed);
+                    buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char(' ').set_bg(Color::LightRed);
+                },
+                RenderCell::Open => {
+                    buf[Position { x: area.x + x * 2, y : area.y + y }].set_char(' ').set_bg(Color::Blue);
+                    buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char(' ').set_bg(Color::Blue);
+                },
+                RenderCell::Visited => {
+                    buf[Position { x: area.x + x * 2, y : area.y + y }].set_char(' ').set_bg(Color::DarkGray);
+                    buf[Position { x: area.x + x * 2 + 1, y : area.y + y }].set_char(' ').set_bg(Color::DarkGray);
+                },
+            }
+
+            // Open passages take the colour of whichever cell on either side is closer.
+            let heat = heat_map.and_then(|heat_map| {
+                let (ry, rx) = (wy.rem_euclid(2), wx.rem_euclid(2));
+                let (cell, signs) = cells[(wy.div_euclid(2) - min_y) as usize][(wx.div_euclid(2) - min_x) as usize]?;
+                let distance = match (ry, rx) {
+                    (1, 1) => None,
+                    _ if maze.is_excluded(cell) => None,
+                    (0, 0) => heat_map.distances[cell.0],
+                    _ => {
+                        let view_axis = if ry == 1 { 0 } else { 1 };
+                        match maze.neighbour(cell, maze.axes[view_axis], signs[view_axis]) {
+                            Some((wall, neighbour_cell)) if !maze.get_wall(&wall) => {
+                                [cell, neighbour_cell].into_iter().filter_map(|cell| heat_map.distances[cell.0]).min()
+                            },
+                            _ => None,
+                        }
+                    },
+                };
+                distance.map(|distance| heat_map.colour(distance))
+            });
+
+            if let Some(color) = heat {
+                for dx in 0..2 {
+                    let buf_cell = &mut buf[Position { x: area.x + x * 2 + dx, y : area.y + y }];
+                    if buf_cell.bg == Color::Reset {
+                        buf_cell.set_bg(color);
+                    }
+                }
             }
         }
     }
@@ -413,9 +1519,146 @@ fn parse_dimension(s: &str) -> Option<Vec<usize>> {
         .ok()
 }
 
+/// Parse the boundary of each axis, returning [Boundary::Wrap] for every axis if empty.
+fn parse_boundaries(s: &str, rank: usize) -> Option<Vec<Boundary>> {
+    if s.trim().is_empty() {
+        return Some(vec![Boundary::Wrap; rank]);
+    }
+
+    let boundaries = s
+        .split(',')
+        .map(|s| s.parse())
+        .try_collect::<Vec<Boundary>>()
+        .ok()?;
+
+    if boundaries.len() != rank {
+        return None;
+    }
+
+    for (axis, boundary) in boundaries.iter().enumerate() {
+        if let Boundary::Twisted(mirror_axis) = boundary && (*mirror_axis == axis || *mirror_axis >= rank) {
+            return None;
+        }
+    }
+
+    Some(boundaries)
+}
+
+fn parse_policy(s: &str) -> Option<SelectionPolicy> {
+    if s.trim().is_empty() {
+        return Some(SelectionPolicy::default());
+    }
+    s.parse().ok()
+}
+
+/// Parse the weight of each axis, returning a weight of 1 for every axis if empty, and [None] if
+/// any weight is outside [Maze::AXIS_WEIGHT_RANGE].
+fn parse_axis_weights(s: &str, rank: usize) -> Option<Vec<f64>> {
+    if s.trim().is_empty() {
+        return Some(vec![1.0; rank]);
+    }
+
+    let axis_weights = s
+        .split(',')
+        .map(|s| s.trim())
+        .map(|s| s.parse())
+        .try_collect::<Vec<f64>>()
+        .ok()?;
+
+    if axis_weights.len() != rank || !axis_weights.iter().all(|weight| Maze::AXIS_WEIGHT_RANGE.contains(weight)) {
+        return None;
+    }
+
+    Some(axis_weights)
+}
+
+fn parse_placement(s: &str) -> Option<Placement> {
+    if s.trim().is_empty() {
+        return Some(Placement::Random);
+    }
+    s.parse().ok()
+}
+
+/// Parse an optional seed, returning [None] if it is invalid and `Some(None)` if it is empty.
+fn parse_seed(s: &str) -> Option<Option<u64>> {
+    if s.trim().is_empty() {
+        return Some(None);
+    }
+    s.trim().parse().ok().map(Some)
+}
+
+/// Memory budget used if none is given in the setup screen, which leaves room for generating a
+/// maze with tens of millions of cells with any generator.
+const DEFAULT_MEMORY_BUDGET: usize = 2 << 30;
+
+/// Parse a number of bytes with an optional binary unit, returning [DEFAULT_MEMORY_BUDGET] if
+/// empty.
+fn parse_memory_budget(s: &str) -> Option<usize> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Some(DEFAULT_MEMORY_BUDGET);
+    }
+
+    let s = s.strip_suffix("iB").or_else(|| s.strip_suffix('B')).unwrap_or(s).trim_end();
+    let (s, shift) = match s.char_indices().last()? {
+        (index, 'K' | 'k') => (&s[..index], 10),
+        (index, 'M' | 'm') => (&s[..index], 20),
+        (index, 'G' | 'g') => (&s[..index], 30),
+        (index, 'T' | 't') => (&s[..index], 40),
+        _ => (s, 0),
+    };
+
+    let count = s.trim().parse::<usize>().ok()?;
+    count.checked_mul(1 << shift)
+}
+
+/// Format a number of bytes using the largest binary unit that keeps it at least 1.
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
 impl Application {
+    /// Time between frames while the maze is being carved out.
+    const FRAME_DURATION: std::time::Duration = std::time::Duration::from_millis(33);
+
+    /// Number of steps shown by a short hint.
+    const HINT_STEPS: usize = 8;
+
     pub fn new() -> Application {
-        Self::Menu { dimension: String::new() }
+        Self::Menu {
+            field: MenuField::Dimension,
+            dimension: String::new(),
+            shape: Shape::Box,
+            boundaries: String::new(),
+            generator: GeneratorKind::Prim,
+            policy: String::new(),
+            room_size: 1,
+            axis_weights: String::new(),
+            placement: String::new(),
+            solver: SolverKind::AStar,
+            hints: 3,
+            checkpoints: 0,
+            doors: 0,
+            one_way: 0,
+            portals: 0,
+            braid: 0,
+            animate: false,
+            seed: String::new(),
+            memory_budget: String::new(),
+        }
     }
 
     pub fn run(&mut self) {
@@ -431,8 +1674,8 @@ impl Application {
 
     pub fn render(&self, frame: &mut Frame) {
         match self {
-            Application::Menu { dimension } => {
-                let text = if dimension.is_empty() {
+            Application::Menu { field, dimension, shape, boundaries, generator, policy, room_size, axis_weights, placement, solver, hints, checkpoints, doors, one_way, portals, braid, animate, seed, memory_budget } => {
+                let dimension_text = if dimension.is_empty() {
                     Text::from(" Enter dimension of maze to be generated here: (e.g. 50, 40, 30) ").style(Style::new().dark_gray())
                 } else {
                     if parse_dimension(dimension).is_some() {
@@ -442,8 +1685,124 @@ impl Application {
                     }
                 };
 
-                let desired_width = (text.width() + 2) as u16;
-                let desired_height = 3;
+                let shape_text = Text::from(format!(" Shape: < {} > ", shape.name()));
+
+                let boundaries_text = if boundaries.is_empty() {
+                    Text::from(" Enter boundary of each axis here, or leave empty to wrap around: (e.g. wrap, bounded, twisted:0) ").style(Style::new().dark_gray())
+                } else {
+                    if parse_dimension(dimension).and_then(|dimension| parse_boundaries(boundaries, dimension.len())).is_some() {
+                        Text::from(format!(" Boundaries: {boundaries} ")).style(Style::new().green())
+                    } else {
+                        Text::from(format!(" Boundaries: {boundaries} ")).style(Style::new().red())
+                    }
+                };
+
+                let generator_text = Text::from(format!(" Generator: < {} > ", generator.name()));
+
+                let policy_text = if policy.is_empty() {
+                    Text::from(" Enter selection policy for growing tree here: (e.g. newest:75, random:25) ").style(Style::new().dark_gray())
+                } else {
+                    if parse_policy(policy).is_some() {
+                        Text::from(format!(" Policy: {policy} ")).style(Style::new().green())
+                    } else {
+                        Text::from(format!(" Policy: {policy} ")).style(Style::new().red())
+                    }
+                };
+
+                let room_size_text = Text::from(format!(" Room Size: < {room_size} > "));
+
+                let axis_weights_text = if axis_weights.is_empty() {
+                    Text::from(" Enter relative weight of each axis here, or leave empty for no bias: (e.g. 4, 1, 0.5) ").style(Style::new().dark_gray())
+                } else {
+                    if parse_dimension(dimension).and_then(|dimension| parse_axis_weights(axis_weights, dimension.len())).is_some() {
+                        Text::from(format!(" Axis Weights: {axis_weights} ")).style(Style::new().green())
+                    } else {
+                        Text::from(format!(" Axis Weights: {axis_weights} ")).style(Style::new().red())
+                    }
+                };
+
+                let placement_text = if placement.is_empty() {
+                    Text::from(" Enter placement of start and end here: (e.g. random, min:100, diameter) ").style(Style::new().dark_gray())
+                } else {
+                    if parse_placement(placement).is_some() {
+                        Text::from(format!(" Placement: {placement} ")).style(Style::new().green())
+                    } else {
+                        Text::from(format!(" Placement: {placement} ")).style(Style::new().red())
+                    }
+                };
+
+                let solver_text = Text::from(format!(" Solver: < {} > ", solver.name()));
+                let hints_text = Text::from(format!(" Hints: < {hints} > "));
+                let checkpoints_text = Text::from(format!(" Checkpoints: < {checkpoints} > "));
+                let doors_text = Text::from(format!(" Doors: < {doors} > "));
+                let one_way_text = Text::from(format!(" One-Way: < {one_way}% > "));
+                let portals_text = Text::from(format!(" Portals: < {portals} > "));
+                let braid_text = Text::from(format!(" Braid: < {braid}% > "));
+                let animate_text = Text::from(format!(" Animate: < {} > ", if *animate { "On" } else { "Off" }));
+
+                let seed_text = if seed.is_empty() {
+                    Text::from(" Enter seed here, or leave empty for a random seed: (e.g. 12345) ").style(Style::new().dark_gray())
+                } else {
+                    if parse_seed(seed).is_some() {
+                        Text::from(format!(" Seed: {seed} ")).style(Style::new().green())
+                    } else {
+                        Text::from(format!(" Seed: {seed} ")).style(Style::new().red())
+                    }
+                };
+
+                let memory_budget_text = if memory_budget.is_empty() {
+                    Text::from(format!(" Enter memory budget here, or leave empty for {}: (e.g. 512M, 4GiB) ", format_bytes(DEFAULT_MEMORY_BUDGET))).style(Style::new().dark_gray())
+                } else {
+                    if parse_memory_budget(memory_budget).is_some() {
+                        Text::from(format!(" Memory Budget: {memory_budget} ")).style(Style::new().green())
+                    } else {
+                        Text::from(format!(" Memory Budget: {memory_budget} ")).style(Style::new().red())
+                    }
+                };
+
+                // Checked before anything is allocated, since a mistyped dimension can easily ask for
+                // more memory than there is.
+                let memory_text = match (parse_dimension(dimension), parse_memory_budget(memory_budget)) {
+                    (Some(dimension), Some(memory_budget)) => {
+                        let policy = parse_policy(policy).unwrap_or_default();
+                        let estimate = Maze::estimate_memory(&dimension, *shape != Shape::Box, *checkpoints as usize, *doors as usize, generator.generator(policy, *room_size as usize).as_ref());
+                        let required = estimate.required(*doors > 0 || *one_way > 0 || *portals > 0);
+                        if estimate.peak() <= memory_budget {
+                            Text::from(format!(" Estimated memory: {} of {} budget ", format_bytes(estimate.peak()), format_bytes(memory_budget))).style(Style::new().green())
+                        } else if required <= memory_budget {
+                            Text::from(format!(" Estimated memory: {} of {} budget, but solving or showing the heat map may take {} ", format_bytes(required), format_bytes(memory_budget), format_bytes(estimate.peak()))).style(Style::new().yellow())
+                        } else {
+                            Text::from(format!(" Estimated memory: {} is over the {} budget. Reduce the dimensions or raise the budget. ", format_bytes(required), format_bytes(memory_budget))).style(Style::new().red())
+                        }
+                    },
+                    _ => Text::from(" Estimated memory: enter a valid dimension and memory budget ").style(Style::new().dark_gray()),
+                };
+
+                let help_text = Text::from(" Up/Down: Select field, Left/Right: Change option, Enter: Generate ").style(Style::new().dark_gray());
+
+                let texts = [
+                    (MenuField::Dimension, dimension_text),
+                    (MenuField::Shape, shape_text),
+                    (MenuField::Boundaries, boundaries_text),
+                    (MenuField::Generator, generator_text),
+                    (MenuField::Policy, policy_text),
+                    (MenuField::RoomSize, room_size_text),
+                    (MenuField::AxisWeights, axis_weights_text),
+                    (MenuField::Placement, placement_text),
+                    (MenuField::Solver, solver_text),
+                    (MenuField::Hints, hints_text),
+                    (MenuField::Checkpoints, checkpoints_text),
+                    (MenuField::Doors, doors_text),
+                    (MenuField::OneWay, one_way_text),
+                    (MenuField::Portals, portals_text),
+                    (MenuField::Braid, braid_text),
+                    (MenuField::Animate, animate_text),
+                    (MenuField::Seed, seed_text),
+                    (MenuField::MemoryBudget, memory_budget_text),
+                ];
+
+                let desired_width = (texts.iter().map(|(_, text)| text.width()).chain([memory_text.width(), help_text.width()]).max().unwrap() + 2) as u16;
+                let desired_height = (texts.len() * 3 + 2) as u16;
 
                 let mut input_area = frame.area();
 
@@ -452,15 +1811,112 @@ impl Application {
                     input_area.width = desired_width;
                 }
 
-                if input_area.height > desired_height {
-                    input_area.y += (input_area.height - desired_height) / 2;
-                    input_area.height = desired_height;
-                }
+                if input_area.height > desired_height {
+                    input_area.y += (input_area.height - desired_height) / 2;
+                    input_area.height = desired_height;
+                }
+
+                let areas = Layout::vertical(texts.iter().map(|_| Constraint::Length(3)).chain([Constraint::Length(1), Constraint::Length(1)])).split(input_area);
+
+                for ((text_field, text), area) in std::iter::zip(texts, areas.iter()) {
+                    let mut block = Block::bordered();
+                    if text_field == *field {
+                        block = block.border_style(Style::new().yellow());
+                    }
+
+                    let input_widget = Paragraph::new(text).block(block);
+                    frame.render_widget(input_widget, *area);
+                }
+
+                frame.render_widget(memory_text, areas[areas.len() - 2]);
+                frame.render_widget(help_text, areas[areas.len() - 1]);
+            },
+            Application::Generating { maze, seed, carved, speed, paused, .. } => {
+                let mut info = Text::default();
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Dimensions: ");
+                    for (i, dimension) in maze.dimensions.iter().enumerate() {
+                        if i != 0 { line.push_span(", "); }
+                        line.push_span(dimension.to_string());
+                    }
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Seed: ");
+                    line.push_span(seed.to_string());
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Generator: ");
+                    line.push_span(maze.generator.as_str());
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span(format!("Walls Carved: {carved}"));
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span(format!("Speed: {speed} walls per frame"));
+                    if *paused {
+                        line.push_span(Span::raw(" (Paused)").style(Style::new().yellow()));
+                    }
+                    info.push_line(line);
+                }
+
+                let mut help = Text::default();
+
+                {
+                    let mut line = Line::default();
+                    line.push_span(if *paused { "Space: Resume" } else { "Space: Pause" });
+                    help.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("+/-: Speed up/Slow down");
+                    help.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Enter: Skip to end");
+                    help.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Esc: Cancel");
+                    help.push_line(line);
+                }
+
+                let [info_area, help_area, maze_area] = Layout::vertical([
+                    Constraint::Length((info.lines.len()+2).try_into().unwrap()),
+                    Constraint::Length((help.lines.len()+2).try_into().unwrap()),
+                    Constraint::Min(0),
+                ]).areas(frame.area());
+
+                let info_block = Block::bordered().title("Info");
+                let help_block = Block::bordered().title("Help");
+
+                frame.render_widget(&info_block, info_area);
+                frame.render_widget(&info, info_block.inner(info_area));
+
+                frame.render_widget(&help_block, help_area);
+                frame.render_widget(&help, help_block.inner(help_area));
 
-                let input_widget = Paragraph::new(text).block(Block::bordered());
-                frame.render_widget(input_widget, input_area);
+                render_maze(maze_area, frame.buffer_mut(), maze, None, None, None, false);
             },
-            Application::Main { maze, view_axis, solution } => {
+            Application::Main { maze, seed, solver, view_axis, solution, hint, hints_used, hint_limit, search, heat_map, end_distances, player_distances, stats } => {
                 let mut info = Text::default();
 
                 {
@@ -490,10 +1946,44 @@ impl Application {
                     info.push_line(line);
                 }
 
+                {
+                    let mut line = Line::default();
+                    line.push_span("Boundaries: ");
+                    for (i, boundary) in maze.boundaries.iter().enumerate() {
+                        if i != 0 { line.push_span(", "); }
+                        line.push_span(boundary.to_string());
+                    }
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Axis Weights: ");
+                    for (i, weight) in maze.axis_weights.iter().enumerate() {
+                        if i != 0 { line.push_span(", "); }
+                        line.push_span(weight.to_string());
+                    }
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Seed: ");
+                    line.push_span(seed.to_string());
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Generator: ");
+                    line.push_span(maze.generator.as_str());
+                    info.push_line(line);
+                }
+
                 {
                     let mut line = Line::default();
                     line.push_span("Position: ");
-                    for (i, dimension) in maze.position.iter().enumerate() {
+                    for (i, dimension) in maze.coordinates(maze.position).iter().enumerate() {
                         if i != 0 { line.push_span(", "); }
                         line.push_span(dimension.to_string());
                     }
@@ -503,7 +1993,7 @@ impl Application {
                 {
                     let mut line = Line::default();
                     line.push_span("Start: ");
-                    for (i, dimension) in maze.start.iter().enumerate() {
+                    for (i, dimension) in maze.coordinates(maze.start).iter().enumerate() {
                         if i != 0 { line.push_span(", "); }
                         line.push_span(dimension.to_string());
                     }
@@ -513,13 +2003,88 @@ impl Application {
                 {
                     let mut line = Line::default();
                     line.push_span("End: ");
-                    for (i, dimension) in maze.end.iter().enumerate() {
+                    for (i, dimension) in maze.coordinates(maze.end).iter().enumerate() {
                         if i != 0 { line.push_span(", "); }
                         line.push_span(dimension.to_string());
                     }
                     info.push_line(line);
                 }
 
+                {
+                    let mut line = Line::default();
+                    line.push_span("Placement: ");
+                    line.push_span(maze.placement.to_string());
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span("Solver: ");
+                    line.push_span(solver.name());
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    let span = Span::raw(format!("Hints: {hints_used}/{hint_limit}"));
+                    line.push_span(if hints_used == hint_limit { span.style(Style::new().red()) } else { span });
+                    info.push_line(line);
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span(format!("Checkpoints: {}/{}", maze.checkpoints_reached, maze.checkpoints.len()));
+                    if maze.is_finished() {
+                        line.push_span(Span::raw(" Finished!").style(Style::new().green()));
+                    }
+                    info.push_line(line);
+                }
+
+                if !maze.keys.is_empty() {
+                    let mut line = Line::default();
+                    line.push_span(format!("Keys: {}/{}", maze.keys_held.count_ones(), maze.keys.len()));
+                    for colour in 0..maze.keys.len() {
+                        let (name, color) = KEY_COLOURS[colour % KEY_COLOURS.len()];
+                        let style = if maze.keys_held & (1 << colour) != 0 { Style::new().fg(color) } else { Style::new().dark_gray() };
+                        line.push_span(" ");
+                        line.push_span(Span::raw(name).style(style));
+                    }
+                    info.push_line(line);
+                }
+
+                if let Some(search) = search {
+                    let mut line = Line::default();
+                    line.push_span(format!("Search: {} expanded, {} open, {} per frame", search.stepper.trace().expanded, search.stepper.open().len(), search.speed));
+                    if search.done {
+                        line.push_span(Span::raw(" (Done)").style(Style::new().green()));
+                    } else if search.paused {
+                        line.push_span(Span::raw(" (Paused)").style(Style::new().yellow()));
+                    }
+                    info.push_line(line);
+                }
+
+                let distances = match heat_map {
+                    Some(HeatSource::End) => end_distances.as_deref(),
+                    Some(HeatSource::Player) => player_distances.as_deref(),
+                    None => None,
+                };
+
+                if let Some(distances) = distances {
+                    let mut line = Line::default();
+                    line.push_span("Heat Map: ");
+                    line.push_span(Span::raw("near").style(Style::new().fg(distances.colour(0))));
+                    line.push_span(" to ");
+                    line.push_span(Span::raw("far").style(Style::new().fg(distances.colour(distances.max_distance))));
+                    line.push_span(format!(" from the {}, up to {} steps", if *heat_map == Some(HeatSource::End) { "end" } else { "current position" }, distances.max_distance));
+                    info.push_line(line);
+                }
+
+                if let Some(Err(no_path)) = solution.as_ref().or(hint.as_ref()) {
+                    let mut line = Line::default();
+                    line.push_span(Span::raw(format!("Goal unreachable: {no_path}")).style(Style::new().red()));
+                    info.push_line(line);
+                }
+
                 let mut help = Text::default();
 
                 match view_axis {
@@ -557,6 +2122,69 @@ impl Application {
                     },
                 }
 
+                match hint {
+                    Some(_) => {
+                        let mut line = Line::default();
+                        line.push_span("h/H: Hide hint");
+                        help.push_line(line);
+                    },
+                    None => {
+                        let mut line = Line::default();
+                        line.push_span(format!("h: Show the next {} steps, H: Show the way to the end", Self::HINT_STEPS));
+                        help.push_line(line);
+                    },
+                }
+
+                match search {
+                    Some(search) => {
+                        let mut line = Line::default();
+                        line.push_span("a: Hide search");
+                        help.push_line(line);
+
+                        if !search.done {
+                            let mut line = Line::default();
+                            line.push_span(if search.paused { "Space: Resume search" } else { "Space: Pause search" });
+                            help.push_line(line);
+
+                            let mut line = Line::default();
+                            line.push_span("+/-: Speed up/Slow down search");
+                            help.push_line(line);
+
+                            let mut line = Line::default();
+                            line.push_span("Enter: Skip to end of search");
+                            help.push_line(line);
+                        }
+                    },
+                    None => {
+                        let mut line = Line::default();
+                        line.push_span("a: Animate search");
+                        help.push_line(line);
+                    },
+                }
+
+                {
+                    let mut line = Line::default();
+                    line.push_span(match heat_map {
+                        None => "d: Show distances from the end",
+                        Some(HeatSource::End) => "d: Show distances from the current position",
+                        Some(HeatSource::Player) => "d: Hide distances",
+                    });
+                    help.push_line(line);
+                }
+
+                match stats {
+                    Some(_) => {
+                        let mut line = Line::default();
+                        line.push_span("t: Hide stats");
+                        help.push_line(line);
+                    },
+                    None => {
+                        let mut line = Line::default();
+                        line.push_span("t: Show stats");
+                        help.push_line(line);
+                    },
+                }
+
                 {
                     let mut line = Line::default();
                     line.push_span("Arrow Keys: Move");
@@ -578,12 +2206,94 @@ impl Application {
                 frame.render_widget(&help_block, help_area);
                 frame.render_widget(&help, help_block.inner(help_area));
 
-                render_maze(maze_area, frame.buffer_mut(), maze, solution.as_ref());
+                match stats {
+                    Some(stats) => {
+                        let stats_block = Block::bordered().title("Stats");
+                        frame.render_widget(&stats_block, maze_area);
+                        frame.render_widget(Text::from(stats.to_string()), stats_block.inner(maze_area));
+                    },
+                    None => {
+                        let route = [solution, hint].into_iter().find_map(|route| route.as_ref()?.as_ref().ok());
+                        render_maze(maze_area, frame.buffer_mut(), maze, route, search.as_ref().map(|search| search.stepper.as_ref()), distances, true);
+                    },
+                }
             },
         }
     }
 
+    /// Knock down as many walls as the speed allows, or all of them if skip is set, and move on to
+    /// the main screen once the generator is done.
+    fn step_generation(&mut self, skip: bool) {
+        let Application::Generating { maze, rng, stepper, carved, speed, .. } = self else {
+            return;
+        };
+
+        let mut steps = 0;
+        loop {
+            if !skip && steps == *speed {
+                return;
+            }
+
+            let Some(wall) = stepper.step(maze, rng) else {
+                break;
+            };
+
+            // Follow the generator around, so that there is always something to see.
+            maze.position = wall.cell;
+            *carved += 1;
+            steps += 1;
+        }
+
+        let Application::Generating { mut maze, seed, mut rng, solver, hints, braid, checkpoints, doors, one_way, portals, .. } = std::mem::replace(self, Application::new()) else {
+            unreachable!()
+        };
+
+        maze.finish_generation(rng.as_mut());
+        maze.braid(braid as f64 / 100.0, rng.as_mut());
+        maze.place_checkpoints(checkpoints as usize, rng.as_mut());
+        maze.place_doors(doors as usize, rng.as_mut());
+        maze.make_one_way(one_way as f64 / 100.0, rng.as_mut());
+        maze.place_portals(portals as usize, rng.as_mut());
+        maze.start();
+        *self = Application::Main { maze, seed, solver, view_axis : None, solution: None, hint: None, hints_used: 0, hint_limit: hints, search: None, heat_map: None, end_distances: None, player_distances: None, stats: None }
+    }
+
+    /// Expand as many states as the speed allows, or carry on to the end of the search if skip is
+    /// set, and show the solution once the search is over.
+    fn step_search(&mut self, skip: bool) {
+        let Application::Main { maze, solution, search: Some(search), .. } = self else {
+            return;
+        };
+
+        let mut steps = 0;
+        while !search.done && (skip || steps < search.speed) {
+            match search.stepper.step(maze) {
+                SearchStep::Expanded => steps += 1,
+                SearchStep::Found(route) => {
+                    *solution = Some(Ok(route));
+                    search.done = true;
+                },
+                SearchStep::Exhausted => {
+                    *solution = Some(Err(NoPath::explain(maze, Progress::start(maze))));
+                    search.done = true;
+                },
+            }
+        }
+    }
+
     pub fn update(&mut self) -> bool {
+        // Keep carving while waiting for input, so that the maze is redrawn every frame.
+        if let Application::Generating { paused: false, .. } = self && !poll(Self::FRAME_DURATION).unwrap() {
+            self.step_generation(false);
+            return true;
+        }
+
+        // Same for a search being shown.
+        if let Application::Main { search: Some(search), .. } = self && !search.paused && !search.done && !poll(Self::FRAME_DURATION).unwrap() {
+            self.step_search(false);
+            return true;
+        }
+
         let event = read().unwrap();
         match event {
             Event::Key(key_event) => match key_event {
@@ -595,28 +2305,175 @@ impl Application {
         };
 
         match self {
-            Application::Menu { dimension } => {
+            Application::Menu { field, dimension, shape, boundaries, generator, policy, room_size, axis_weights, placement, solver, hints, checkpoints, doors, one_way, portals, braid, animate, seed, memory_budget } => {
                 match event {
                     Event::Key(key_event) => match key_event {
-                        KeyEvent { code : KeyCode::Char(c), .. } => { dimension.push(c); },
-                        KeyEvent { code : KeyCode::Esc, .. } => { dimension.clear(); },
-                        KeyEvent { code : KeyCode::Backspace, .. } => { dimension.pop(); },
+                        KeyEvent { code : KeyCode::Up, .. } => *field = field.prev(),
+                        KeyEvent { code : KeyCode::Down | KeyCode::Tab, .. } => *field = field.next(),
                         KeyEvent { code : KeyCode::Enter, .. } => {
-                            if let Some(dimension) = parse_dimension(dimension) {
-                                let mut maze = Maze::new(dimension);
-                                maze.generate(&mut rand::rng());
-                                maze.start();
-                                *self = Application::Main { maze, view_axis : None, solution: None }
+                            if let Some(dimension) = parse_dimension(dimension)
+                                && let Some(boundaries) = parse_boundaries(boundaries, dimension.len())
+                                && let Some(policy) = parse_policy(policy)
+                                && let Some(axis_weights) = parse_axis_weights(axis_weights, dimension.len())
+                                && let Some(placement) = parse_placement(placement)
+                                && let Some(seed) = parse_seed(seed)
+                                && let Some(memory_budget) = parse_memory_budget(memory_budget)
+                                && Maze::estimate_memory(&dimension, *shape != Shape::Box, *checkpoints as usize, *doors as usize, generator.generator(policy.clone(), *room_size as usize).as_ref())
+                                    .required(*doors > 0 || *one_way > 0 || *portals > 0) <= memory_budget
+                            {
+                                let seed = seed.unwrap_or_else(|| rand::rng().random());
+                                let mut rng = StdRng::seed_from_u64(seed);
+                                let mut maze = Maze::with_boundaries(dimension, boundaries);
+                                maze.set_axis_weights(axis_weights);
+                                maze.set_placement(placement);
+
+                                if *shape != Shape::Box {
+                                    let mask = maze.cells()
+                                        .map(|cell| shape.contains(&maze.dimensions, &maze.coordinates(cell)))
+                                        .collect::<BitSet>();
+
+                                    // The maze is too small for anything to be left of the shape.
+                                    if mask.count_ones() == 0 {
+                                        return true;
+                                    }
+
+                                    maze.set_mask(mask);
+                                }
+
+                                let stepper = maze.start_generation(generator.generator(policy, *room_size as usize).as_ref(), &mut rng);
+                                let animate = *animate;
+
+                                // Start slow enough to follow what is going on, but not so slow that
+                                // it takes forever for a large maze.
+                                let speed = (maze.cell_count() / 300).max(1);
+                                *self = Application::Generating { maze, seed, rng: Box::new(rng), stepper, carved: 0, speed, paused: false, solver: *solver, hints: *hints, braid: *braid, checkpoints: *checkpoints, doors: *doors, one_way: *one_way, portals: *portals };
+                                if !animate {
+                                    self.step_generation(true);
+                                }
                             }
                         },
-                        _ => {},
+                        _ => match field {
+                            MenuField::Dimension => match key_event {
+                                KeyEvent { code : KeyCode::Char(c), .. } => { dimension.push(c); },
+                                KeyEvent { code : KeyCode::Esc, .. } => { dimension.clear(); },
+                                KeyEvent { code : KeyCode::Backspace, .. } => { dimension.pop(); },
+                                _ => {},
+                            },
+                            MenuField::Shape => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *shape = shape.prev(),
+                                KeyEvent { code : KeyCode::Right, .. } => *shape = shape.next(),
+                                _ => {},
+                            },
+                            MenuField::Boundaries => match key_event {
+                                KeyEvent { code : KeyCode::Char(c), .. } => { boundaries.push(c); },
+                                KeyEvent { code : KeyCode::Esc, .. } => { boundaries.clear(); },
+                                KeyEvent { code : KeyCode::Backspace, .. } => { boundaries.pop(); },
+                                _ => {},
+                            },
+                            MenuField::Generator => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *generator = generator.prev(),
+                                KeyEvent { code : KeyCode::Right, .. } => *generator = generator.next(),
+                                _ => {},
+                            },
+                            MenuField::Policy => match key_event {
+                                KeyEvent { code : KeyCode::Char(c), .. } => { policy.push(c); },
+                                KeyEvent { code : KeyCode::Esc, .. } => { policy.clear(); },
+                                KeyEvent { code : KeyCode::Backspace, .. } => { policy.pop(); },
+                                _ => {},
+                            },
+                            MenuField::AxisWeights => match key_event {
+                                KeyEvent { code : KeyCode::Char(c), .. } => { axis_weights.push(c); },
+                                KeyEvent { code : KeyCode::Esc, .. } => { axis_weights.clear(); },
+                                KeyEvent { code : KeyCode::Backspace, .. } => { axis_weights.pop(); },
+                                _ => {},
+                            },
+                            MenuField::Placement => match key_event {
+                                KeyEvent { code : KeyCode::Char(c), .. } => { placement.push(c); },
+                                KeyEvent { code : KeyCode::Esc, .. } => { placement.clear(); },
+                                KeyEvent { code : KeyCode::Backspace, .. } => { placement.pop(); },
+                                _ => {},
+                            },
+                            MenuField::RoomSize => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *room_size = room_size.saturating_sub(1).max(1),
+                                KeyEvent { code : KeyCode::Right, .. } => *room_size = (*room_size + 1).min(99),
+                                _ => {},
+                            },
+                            MenuField::Solver => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *solver = solver.prev(),
+                                KeyEvent { code : KeyCode::Right, .. } => *solver = solver.next(),
+                                _ => {},
+                            },
+                            MenuField::Hints => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *hints = hints.saturating_sub(1),
+                                KeyEvent { code : KeyCode::Right, .. } => *hints = (*hints + 1).min(99),
+                                _ => {},
+                            },
+                            MenuField::Checkpoints => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *checkpoints = checkpoints.saturating_sub(1),
+                                KeyEvent { code : KeyCode::Right, .. } => *checkpoints = (*checkpoints + 1).min(99),
+                                _ => {},
+                            },
+                            MenuField::Doors => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *doors = doors.saturating_sub(1),
+                                KeyEvent { code : KeyCode::Right, .. } => *doors = (*doors + 1).min(KEY_COLOURS.len() as u32),
+                                _ => {},
+                            },
+                            MenuField::OneWay => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *one_way = one_way.saturating_sub(10),
+                                KeyEvent { code : KeyCode::Right, .. } => *one_way = (*one_way + 10).min(100),
+                                _ => {},
+                            },
+                            MenuField::Portals => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *portals = portals.saturating_sub(1),
+                                KeyEvent { code : KeyCode::Right, .. } => *portals = (*portals + 1).min(9),
+                                _ => {},
+                            },
+                            MenuField::Braid => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *braid = braid.saturating_sub(10),
+                                KeyEvent { code : KeyCode::Right, .. } => *braid = (*braid + 10).min(100),
+                                _ => {},
+                            },
+                            MenuField::Animate => match key_event {
+                                KeyEvent { code : KeyCode::Left, .. } => *animate = !*animate,
+                                KeyEvent { code : KeyCode::Right, .. } => *animate = !*animate,
+                                _ => {},
+                            },
+                            MenuField::Seed => match key_event {
+                                KeyEvent { code : KeyCode::Char(c), .. } => { seed.push(c); },
+                                KeyEvent { code : KeyCode::Esc, .. } => { seed.clear(); },
+                                KeyEvent { code : KeyCode::Backspace, .. } => { seed.pop(); },
+                                _ => {},
+                            },
+                            MenuField::MemoryBudget => match key_event {
+                                KeyEvent { code : KeyCode::Char(c), .. } => { memory_budget.push(c); },
+                                KeyEvent { code : KeyCode::Esc, .. } => { memory_budget.clear(); },
+                                KeyEvent { code : KeyCode::Backspace, .. } => { memory_budget.pop(); },
+                                _ => {},
+                            },
+                        },
                     },
                     _ => {},
                 }
             },
-            Application::Main { maze, view_axis, solution } => {
+            Application::Generating { speed, paused, .. } => {
+                if let Event::Key(key_event) = event {
+                    match key_event {
+                        KeyEvent { code : KeyCode::Char(' '), .. } => *paused = !*paused,
+                        KeyEvent { code : KeyCode::Char('+' | '='), .. } => *speed = speed.saturating_mul(2),
+                        KeyEvent { code : KeyCode::Char('-'), .. } => *speed = (*speed / 2).max(1),
+                        KeyEvent { code : KeyCode::Enter, .. } => self.step_generation(true),
+                        KeyEvent { code : KeyCode::Esc, .. } => *self = Application::new(),
+                        _ => {},
+                    }
+                }
+            },
+            Application::Main { maze, solver, view_axis, solution, hint, hints_used, hint_limit, search, heat_map, stats, .. } => {
                 match event {
                     Event::Key(key_event) => match key_event {
+                        KeyEvent { code : KeyCode::Char(' '), .. } if let Some(search) = search => search.paused = !search.paused,
+                        KeyEvent { code : KeyCode::Char('+' | '='), .. } if let Some(search) = search => search.speed = search.speed.saturating_mul(2),
+                        KeyEvent { code : KeyCode::Char('-'), .. } if let Some(search) = search => search.speed = (search.speed / 2).max(1),
+                        KeyEvent { code : KeyCode::Enter, .. } if search.is_some() => self.step_search(true),
                         KeyEvent { code : KeyCode::Up, .. } => maze.walk(0, false),
                         KeyEvent { code : KeyCode::Down, .. } => maze.walk(0, true),
                         KeyEvent { code : KeyCode::Left, .. } => maze.walk(1, false),
@@ -639,7 +2496,48 @@ impl Application {
 
                         KeyEvent { code : KeyCode::Char('s'), .. } => {
                             if solution.take().is_none() {
-                                *solution = Some(maze.solve());
+                                *solution = Some(maze.solve_with(solver.solver().as_ref()));
+                            }
+                        },
+
+                        KeyEvent { code : KeyCode::Char(c @ ('h' | 'H')), .. } => {
+                            if hint.take().is_none() && *hints_used < *hint_limit && !maze.is_finished() {
+                                // Finding out that the end is out of reach does not use up a hint.
+                                let mut route = maze.solve_from_position(solver.solver().as_ref());
+                                if let Ok(route) = &mut route {
+                                    if c == 'h' {
+                                        route.truncate(Self::HINT_STEPS + 1);
+                                    }
+                                    *hints_used += 1;
+                                }
+                                *hint = Some(route);
+                            }
+                        },
+
+                        KeyEvent { code : KeyCode::Char('a'), .. } => {
+                            if search.take().is_none() {
+                                // The solution is shown again once the search finds it.
+                                *solution = None;
+                                *search = Some(Box::new(SearchAnimation {
+                                    stepper: solver.solver().start(maze, Progress::start(maze)),
+                                    speed: (maze.cell_count() / 300).max(1),
+                                    paused: false,
+                                    done: false,
+                                }));
+                            }
+                        },
+
+                        KeyEvent { code : KeyCode::Char('d'), .. } => {
+                            *heat_map = match heat_map {
+                                None => Some(HeatSource::End),
+                                Some(HeatSource::End) => Some(HeatSource::Player),
+                                Some(HeatSource::Player) => None,
+                            };
+                        },
+
+                        KeyEvent { code : KeyCode::Char('t'), .. } => {
+                            if stats.take().is_none() {
+                                *stats = Some(Box::new(MazeStats::new(maze)));
                             }
                         },
 
@@ -650,6 +2548,26 @@ impl Application {
             },
         }
 
+        // Only work out the distances shown in the heat map once they are needed, and only again
+        // once they are out of date.
+        if let Application::Main { maze, heat_map: Some(source), end_distances, player_distances, .. } = self {
+            let (distances, origin) = match source {
+                HeatSource::End => (end_distances, maze.end),
+                HeatSource::Player => (player_distances, maze.position),
+            };
+            if distances.as_ref().is_none_or(|distances| distances.origin != origin) {
+                *distances = Some(Box::new(DistanceField::new(maze, origin)));
+            }
+        }
+
+        // Keep showing the rest of the hint while following it, but drop it once we stray off it.
+        if let Application::Main { maze, hint, .. } = self && let Some(Ok(route)) = hint {
+            match route.iter().position(|cell| *cell == maze.position) {
+                Some(index) => { route.drain(..index); },
+                None => *hint = None,
+            }
+        }
+
         true
     }
 }
@@ -657,3 +2575,445 @@ impl Application {
 fn main() {
     Application::new().run()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::generator::Kruskal;
+    use crate::solver::Waypoint;
+    use crate::test_support::{braided_maze, check_route};
+
+    use std::collections::VecDeque;
+
+    #[test]
+    fn test_seed() {
+        for kind in GeneratorKind::ALL {
+            let mazes = [0, 1].map(|_| {
+                let mut rng = StdRng::seed_from_u64(0x7c3a95e2d04b18f6);
+                let mut maze = Maze::new(vec![3, 4, 5]);
+                maze.generate(kind.generator(SelectionPolicy::default(), 1).as_ref(), &mut rng);
+                maze.braid(0.5, &mut rng);
+                maze
+            });
+
+            assert_eq!(mazes[0].walls, mazes[1].walls);
+            assert_eq!(mazes[0].start, mazes[1].start);
+            assert_eq!(mazes[0].end, mazes[1].end);
+        }
+    }
+
+    fn dead_end_count(maze: &Maze) -> usize {
+        maze.cells()
+            .filter(|cell| maze.is_dead_end(*cell))
+            .count()
+    }
+
+    #[test]
+    fn test_traverse() {
+        let maze = Maze::with_boundaries(vec![3, 4, 5], vec![Boundary::Twisted(1), Boundary::Bounded, Boundary::Wrap]);
+        for cell in maze.cells() {
+            assert_eq!(maze.cell_id(&maze.coordinates(cell)), cell);
+            for axis in 0..3 {
+                for sign in [false, true] {
+                    if let Some(neighbour_cell) = maze.traverse(cell, axis, sign) {
+                        assert_eq!(maze.traverse(neighbour_cell, axis, !sign), Some(cell));
+                    }
+                }
+            }
+        }
+
+        let traverse = |position: &[usize], axis, sign| maze.traverse(maze.cell_id(position), axis, sign).map(|cell| maze.coordinates(cell));
+        assert_eq!(traverse(&[2, 1, 0], 0, true), Some(vec![0, 2, 0]));
+        assert_eq!(traverse(&[0, 3, 0], 1, true), None);
+        assert_eq!(traverse(&[0, 0, 0], 2, false), Some(vec![0, 0, 4]));
+        assert_eq!(traverse(&[1, 2, 3], 1, false), Some(vec![1, 1, 3]));
+    }
+
+    #[test]
+    fn test_view_cells() {
+        let mazes = [
+            (vec![4, 5], vec![Boundary::Twisted(1), Boundary::Wrap], 0),
+            (vec![4, 5], vec![Boundary::Twisted(1), Boundary::Bounded], 0),
+            (vec![3, 4, 5], vec![Boundary::Bounded, Boundary::Wrap, Boundary::Twisted(1)], 2),
+        ];
+
+        for (dimensions, boundaries, axis) in mazes {
+            let mut maze = Maze::with_boundaries(dimensions, boundaries);
+            maze.set_view_axis(0, axis);
+            for cell in maze.cells() {
+                // Squares next to each other on screen must be neighbours in the maze, across
+                // twisted edges as well.
+                maze.position = cell;
+                let cells = view_cells(&maze, -6, 6, -6, 6);
+                for (y, row) in cells.iter().enumerate() {
+                    for (x, square) in row.iter().enumerate() {
+                        let Some((cell, signs)) = *square else {
+                            continue;
+                        };
+                        let neighbour_cell = |view_axis: usize| maze.neighbour(cell, maze.axes[view_axis], signs[view_axis]).map(|(_, cell)| cell);
+                        if let Some(next_row) = cells.get(y + 1) {
+                            assert_eq!(neighbour_cell(0), next_row[x].map(|(cell, _)| cell));
+                        }
+                        if let Some(next_square) = row.get(x + 1) {
+                            assert_eq!(neighbour_cell(1), next_square.map(|(cell, _)| cell));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut maze = Maze::with_boundaries(vec![4, 5], vec![Boundary::Twisted(1), Boundary::Wrap]);
+        maze.position = maze.cell_id(&[3, 0]);
+        let cells = view_cells(&maze, 0, 1, 0, 2);
+        let below = |x: usize| cells[1][x].map(|(cell, _)| maze.coordinates(cell));
+        assert_eq!(below(1), Some(vec![0, 3]));
+        assert_eq!(below(2), Some(vec![0, 2]));
+    }
+
+    #[test]
+    fn test_distance() {
+        let boundaries = [
+            vec![Boundary::Wrap, Boundary::Wrap, Boundary::Wrap],
+            vec![Boundary::Bounded, Boundary::Wrap, Boundary::Bounded],
+            vec![Boundary::Twisted(1), Boundary::Bounded, Boundary::Twisted(0)],
+        ];
+
+        for boundaries in boundaries {
+            let exact = !boundaries.iter().any(|boundary| matches!(boundary, Boundary::Twisted(_)));
+
+            // With every wall knocked down, the breadth first search distance is the length of the
+            // shortest path between two cells, which the heuristic must never overestimate.
+            let mut maze = Maze::with_boundaries(vec![3, 4, 5], boundaries);
+            maze.walls.fill(false);
+
+            for start in maze.cells() {
+                let distances = maze.reachable(start);
+                assert_eq!(distances.len(), maze.cell_count());
+                for (cell, distance) in distances {
+                    if exact {
+                        assert_eq!(maze.distance(start, cell), distance);
+                    } else {
+                        assert!(maze.distance(start, cell) <= distance);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_braid() {
+        let mut rng = StdRng::seed_from_u64(0x1d5e8b70a3f49c62);
+
+        let mut maze = Maze::new(vec![3, 4, 5]);
+        maze.generate(&Kruskal, &mut rng);
+
+        let walls = maze.walls.clone();
+        maze.braid(0.0, &mut rng);
+        assert_eq!(maze.walls, walls);
+        assert_ne!(dead_end_count(&maze), 0);
+
+        maze.braid(1.0, &mut rng);
+        assert_eq!(dead_end_count(&maze), 0);
+
+        // Only walls can have been knocked down, so the maze is still connected.
+        assert!(std::iter::zip(maze.walls.iter(), walls.iter()).all(|(wall, old_wall)| !wall || old_wall));
+    }
+
+    #[test]
+    fn test_placement() {
+        let mut rng = StdRng::seed_from_u64(0x94b2e07d3c6a5f18);
+
+        for _ in 0..16 {
+            let mut maze = Maze::new(vec![6, 7, 5]);
+            maze.set_placement(Placement::Diameter);
+            maze.generate(&Kruskal, &mut rng);
+
+            // No cell can be farther from any other cell than the end is from the start.
+            let diameter = maze.reachable(maze.start).last().unwrap().1;
+            assert_eq!(maze.reachable(maze.start).iter().find(|(cell, _)| *cell == maze.end).unwrap().1, diameter);
+            for cell in maze.cells() {
+                assert!(maze.reachable(cell).last().unwrap().1 <= diameter);
+            }
+
+            maze.set_placement(Placement::MinDistance(diameter / 2));
+            maze.generate(&Kruskal, &mut rng);
+            let distance = maze.reachable(maze.start).iter().find(|(cell, _)| *cell == maze.end).unwrap().1;
+            assert!(distance >= diameter / 2 || distance == maze.reachable(maze.start).last().unwrap().1);
+        }
+
+        assert_eq!("min: 12".parse(), Ok(Placement::MinDistance(12)));
+        assert_eq!("diameter".parse(), Ok(Placement::Diameter));
+        assert_eq!("max:3".parse::<Placement>(), Err(()));
+    }
+
+    #[test]
+    fn test_checkpoints() {
+        let mut rng = StdRng::seed_from_u64(0x3e6f1a08c5d29b74);
+
+        let mut maze = Maze::new(vec![6, 7, 5]);
+        maze.generate(&Kruskal, &mut rng);
+        maze.braid(0.5, &mut rng);
+        maze.place_checkpoints(4, &mut rng);
+        assert_eq!(maze.checkpoints.len(), 4);
+
+        let route = maze.solve().unwrap();
+        assert_eq!(route.first(), Some(&maze.start));
+        assert_eq!(route.last(), Some(&maze.end));
+
+        // Every step of the route must go through an open wall.
+        for step in route.windows(2) {
+            assert!(maze.neighbours(step[0]).any(|(wall, cell)| cell == step[1] && !maze.get_wall(&wall)));
+        }
+
+        // The checkpoints must be visited in order, and each leg must be a shortest path.
+        let mut length = 0;
+        let mut remaining = route.iter();
+        let mut waypoints = vec![maze.start];
+        waypoints.extend(maze.checkpoints.iter().copied());
+        waypoints.push(maze.end);
+        for waypoint in waypoints.windows(2) {
+            assert!(remaining.any(|cell| *cell == waypoint[1]));
+            length += maze.reachable(waypoint[0]).into_iter().find(|(cell, _)| *cell == waypoint[1]).unwrap().1;
+        }
+        assert_eq!(route.len(), length + 1);
+    }
+
+    #[test]
+    fn test_doors() {
+        let mut rng = StdRng::seed_from_u64(0x5e2c94a1f7d03b68);
+        for braid in [0.0, 0.5] {
+            let mut maze = braided_maze(vec![8, 7, 5], braid, &mut rng);
+            maze.place_checkpoints(2, &mut rng);
+            let plain_length = maze.solve().unwrap().len();
+
+            maze.place_doors(3, &mut rng);
+            maze.start();
+            assert_eq!(maze.doors.len(), 3);
+            assert_eq!(maze.keys.len(), 3);
+
+            // Following the route must only ever go through doors whose key has been picked up.
+            let route = maze.solve().unwrap();
+            assert!(route.len() >= plain_length);
+            check_route(&maze, &route);
+
+            // Walking into a door is refused until its key is held.
+            let (wall, colour) = maze.doors.iter().map(|(wall, colour)| (*wall, *colour)).next().unwrap();
+            maze.position = wall.cell;
+            maze.keys_held = 0;
+            maze.set_view_axis(0, wall.axis);
+            maze.walk(0, true);
+            assert_eq!(maze.position, wall.cell);
+
+            maze.keys_held = 1 << colour;
+            maze.walk(0, true);
+            assert_eq!(Some(maze.position), maze.wall_cells(&wall).map(|cells| cells[1]));
+        }
+    }
+
+    #[test]
+    fn test_key_placement() {
+        let mut rng = StdRng::seed_from_u64(0xc81f5a3e62d9b074);
+        for _ in 0..200 {
+            let mut maze = Maze::new(vec![4, 4]);
+            maze.generate(&Kruskal, &mut rng);
+            maze.place_checkpoints(3, &mut rng);
+            maze.place_doors(4, &mut rng);
+            assert_eq!(maze.keys.len(), maze.doors.len());
+
+            // Every key must have a cell of its own that is not hidden under another marker.
+            let cells = maze.keys.iter().collect::<HashSet<_>>();
+            assert_eq!(cells.len(), maze.keys.len());
+            for cell in cells {
+                assert!(*cell != maze.start && *cell != maze.end && !maze.checkpoints.contains(cell));
+            }
+            assert!(maze.solve().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_distance_field() {
+        let mut rng = StdRng::seed_from_u64(0x4f1e8a2c7b39d650);
+        let mut maze = Maze::new(vec![7, 6, 5]);
+        maze.generate(&Kruskal, &mut rng);
+        maze.braid(0.5, &mut rng);
+
+        let field = maze.distance_field(maze.end);
+        let reachable = maze.reachable(maze.end);
+        assert_eq!(field.iter().flatten().count(), reachable.len());
+        for (cell, distance) in reachable {
+            assert_eq!(field[cell.0], Some(distance));
+        }
+
+        // One-way passages count either way, so the distance is the same both ways.
+        maze.make_one_way(0.5, &mut rng);
+        let field = maze.distance_field(maze.end);
+        for cell in maze.cells() {
+            assert_eq!(maze.distance_field(cell)[maze.end.0], field[cell.0]);
+        }
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let mut rng = StdRng::seed_from_u64(0x83d6f1a04c9e27b5);
+        let mut maze = Maze::new(vec![6, 6]);
+        maze.generate(&Kruskal, &mut rng);
+        maze.place_doors(1, &mut rng);
+        maze.start();
+        assert!(maze.solve().is_ok());
+
+        // There is only one way to the end in a perfect maze, so moving the key to the end leaves
+        // the door in the way locked for good.
+        let key = maze.keys[0];
+        maze.keys[0] = maze.end;
+        for kind in SolverKind::ALL {
+            assert_eq!(maze.solve_with(kind.solver().as_ref()), Err(NoPath::Locked(Waypoint::End)));
+        }
+        maze.keys[0] = key;
+
+        maze.place_checkpoints(1, &mut rng);
+        assert!(maze.solve().is_ok());
+
+        let checkpoint = maze.checkpoints[0];
+        for (wall, _) in maze.neighbours(checkpoint).collect::<Vec<_>>() {
+            maze.set_wall(&wall, true);
+        }
+        for kind in SolverKind::ALL {
+            assert_eq!(maze.solve_with(kind.solver().as_ref()), Err(NoPath::Unreachable(Waypoint::Checkpoint(0))));
+        }
+    }
+
+    #[test]
+    fn test_one_way() {
+        let mut rng = StdRng::seed_from_u64(0xb40e7d2a916c53f8);
+        for fraction in [0.3, 1.0] {
+            let mut maze = braided_maze(vec![8, 7, 5], 0.5, &mut rng);
+            maze.place_checkpoints(2, &mut rng);
+            maze.place_doors(2, &mut rng);
+            maze.make_one_way(fraction, &mut rng);
+            maze.start();
+            assert!(maze.one_way.count_ones() > 0);
+
+            // Following the route must never go through a one-way passage the wrong way.
+            check_route(&maze, &maze.solve().unwrap());
+
+            // Walking against a one-way passage is refused, while walking with it is not.
+            let index = (0..maze.walls.len()).find(|index| maze.one_way.get(*index)).unwrap();
+            let wall = maze.wall_from_index(index);
+            let [cell1, cell2] = maze.wall_cells(&wall).unwrap();
+            let (from, to) = if maze.one_way_direction(&wall) == Some(true) { (cell1, cell2) } else { (cell2, cell1) };
+            maze.set_view_axis(0, wall.axis);
+            maze.keys_held = u32::MAX;
+
+            maze.position = to;
+            maze.walk(0, from != cell1);
+            assert_eq!(maze.position, to);
+
+            maze.position = from;
+            maze.walk(0, from == cell1);
+            assert_eq!(maze.position, to);
+        }
+    }
+
+    #[test]
+    fn test_portals() {
+        let mut rng = StdRng::seed_from_u64(0x73a1c0e95f28d46b);
+        for braid in [0.0, 0.5] {
+            let mut maze = braided_maze(vec![9, 8, 6], braid, &mut rng);
+            maze.place_portals(4, &mut rng);
+            maze.start();
+            assert_eq!(maze.portals.len(), 4);
+
+            // Breadth first search from the start, moving on through portals, which A* has to agree
+            // with. Its heuristic allows for going through whichever portal is closest, so portals
+            // that cut the route short must never make it overestimate.
+            let mut distances = vec![usize::MAX; maze.cell_count()];
+            let mut queue = VecDeque::from([maze.start]);
+            distances[maze.start.0] = 0;
+            while let Some(cell) = queue.pop_front() {
+                for (wall, neighbour_cell) in maze.neighbours(cell) {
+                    let neighbour_cell = maze.portal(neighbour_cell).unwrap_or(neighbour_cell);
+                    if maze.can_pass(cell, &wall, 0) && distances[neighbour_cell.0] == usize::MAX {
+                        distances[neighbour_cell.0] = distances[cell.0] + 1;
+                        queue.push_back(neighbour_cell);
+                    }
+                }
+            }
+
+            let route = maze.solve().unwrap();
+            assert_eq!(route.len() - 1, distances[maze.end.0]);
+            check_route(&maze, &route);
+
+            // Walking onto a portal moves on to the other end.
+            let [cell1, cell2] = maze.portals[0];
+            let (wall, neighbour_cell) = maze.neighbours(cell1).find(|(wall, cell)| !maze.get_wall(wall) && maze.portal(*cell).is_none()).unwrap();
+            let sign = wall.cell == neighbour_cell;
+            maze.set_view_axis(0, wall.axis);
+            maze.position = neighbour_cell;
+            maze.walk(0, sign);
+            assert_eq!(maze.position, cell2);
+        }
+    }
+
+    #[test]
+    fn test_portals_first() {
+        let mut rng = StdRng::seed_from_u64(0x1b96d4f07e3a58c2);
+        for _ in 0..20 {
+            let mut maze = Maze::new(vec![9, 8, 6]);
+            maze.generate(&Kruskal, &mut rng);
+            maze.place_checkpoints(2, &mut rng);
+            maze.place_portals(4, &mut rng);
+            maze.place_doors(3, &mut rng);
+            maze.make_one_way(0.3, &mut rng);
+            maze.start();
+
+            // The solution may jump through portals, which must not stop doors and one-way
+            // passages from being placed along it, nor cut the end off.
+            assert_eq!(maze.doors.len(), 3);
+            assert!(maze.keys.iter().all(|cell| maze.portal(*cell).is_none()));
+            for kind in SolverKind::ALL {
+                check_route(&maze, &maze.solve_with(kind.solver().as_ref()).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_axis_weights() {
+        assert_eq!(parse_axis_weights("", 2), Some(vec![1.0, 1.0]));
+        assert_eq!(parse_axis_weights("4, 0.5", 2), Some(vec![4.0, 0.5]));
+        assert_eq!(parse_axis_weights("100, 0.01", 2), Some(vec![100.0, 0.01]));
+
+        // Weights this extreme would overflow or divide by almost zero while generating.
+        for s in ["1e308, 1e308, 1e308", "1e-320, 1, 1", "1000, 1, 1", "0, 1, 1", "-1, 1, 1", "inf, 1, 1", "NaN, 1, 1", "1, 1"] {
+            assert_eq!(parse_axis_weights(s, 3), None, "{s}");
+        }
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        assert_eq!(parse_memory_budget(""), Some(DEFAULT_MEMORY_BUDGET));
+        assert_eq!(parse_memory_budget("4096"), Some(4096));
+        assert_eq!(parse_memory_budget("512M"), Some(512 << 20));
+        assert_eq!(parse_memory_budget("2 GiB"), Some(2 << 30));
+        assert_eq!(parse_memory_budget("1TB"), Some(1 << 40));
+        assert_eq!(parse_memory_budget("lots"), None);
+
+        // Small mazes fit comfortably, and the walls take up a bit per wall.
+        for kind in GeneratorKind::ALL {
+            let generator = kind.generator(SelectionPolicy::default(), 1);
+            let estimate = Maze::estimate_memory(&[50, 40, 30], false, 0, 0, generator.as_ref());
+            assert!(estimate.required(false) >= BitSet::memory(50 * 40 * 30 * 3));
+            assert!(estimate.peak() <= DEFAULT_MEMORY_BUDGET);
+
+            // Absurd dimensions must be caught before anything is allocated, without overflowing.
+            let estimate = Maze::estimate_memory(&[100000, 100000, 100000, 100000], true, 99, KEY_COLOURS.len(), generator.as_ref());
+            assert!(estimate.required(false) > DEFAULT_MEMORY_BUDGET);
+        }
+
+        // A very long maze can be generated, even if solving it might not fit.
+        for kind in [GeneratorKind::Eller, GeneratorKind::Prim] {
+            let estimate = Maze::estimate_memory(&[5, 5, 1000000], false, 0, 0, kind.generator(SelectionPolicy::default(), 1).as_ref());
+            assert!(estimate.required(false) <= DEFAULT_MEMORY_BUDGET);
+        }
+    }
+}
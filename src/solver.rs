@@ -0,0 +1,795 @@
+use std::collections::VecDeque;
+
+use crate::{CellId, Maze};
+use crate::binary_heap::{BinaryHashHeap, BinaryHashHeapItem, PushAction};
+use crate::bitset::BitSet;
+
+/// Algorithm used to find the route returned by [Maze::solve], from the start through every
+/// checkpoint in order to the end, picking up keys to get through doors on the way.
+///
+/// Keys are never lost and checkpoints are visited in order, so a solver searches over states made
+/// up of a cell, the keys held and the number of checkpoints reached, rather than over cells.
+/// Without any doors or checkpoints, this is just a search over cells. Which way a leg between
+/// checkpoints goes can matter for later legs, as it decides which keys are held and which one-way
+/// passages are left behind, so the legs are not searched separately.
+///
+/// Every solver must return a shortest route, or [None] if the end cannot be reached.
+pub trait Solver {
+    /// Set up the state needed to search on from the given progress, which is then searched one
+    /// state at a time by [SolverStepper::step].
+    fn start(&self, maze: &Maze, from: Progress) -> Box<dyn SolverStepper>;
+
+    /// Find a shortest route on from the given progress, e.g. [Progress::start] to solve the whole
+    /// maze.
+    fn solve(&self, maze: &Maze, from: Progress) -> Option<Vec<CellId>> {
+        let mut stepper = self.start(maze, from);
+        loop {
+            match stepper.step(maze) {
+                SearchStep::Expanded => {},
+                SearchStep::Found(route) => return Some(route),
+                SearchStep::Exhausted => return None,
+            }
+        }
+    }
+}
+
+/// Outcome of a single [SolverStepper::step].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchStep {
+    /// A state was expanded and the search goes on. No other outcome expands a state.
+    Expanded,
+    /// The search is over and found this shortest route.
+    Found(Vec<CellId>),
+    /// The search is over without ever getting to the end.
+    Exhausted,
+}
+
+/// Solver part way through a search, so that the search can be shown as it goes.
+///
+/// The maze passed to every step must be the one the solver was started on, and must not be
+/// modified in between. A stepper must not be stepped again once the search is over.
+pub trait SolverStepper {
+    /// Expand the next state.
+    fn step(&mut self, maze: &Maze) -> SearchStep;
+
+    /// Cells of the states waiting to be expanded, with a cell listed once for every such state.
+    fn open(&self) -> Vec<CellId>;
+
+    /// Record of the states expanded so far.
+    fn trace(&self) -> &SearchTrace;
+}
+
+/// States expanded by a search so far, as far as can be seen from their cells.
+#[derive(Debug, Clone)]
+pub struct SearchTrace {
+    /// Cells of the states expanded so far.
+    pub visited: BitSet,
+    /// Cell of the state expanded last.
+    pub current: Option<CellId>,
+    /// Number of states expanded so far.
+    pub expanded: usize,
+}
+
+impl SearchTrace {
+    fn new(cell_count: usize) -> Self {
+        Self {
+            visited: BitSet::new(cell_count, false),
+            current: None,
+            expanded: 0,
+        }
+    }
+
+    fn expand(&mut self, cell: CellId) {
+        self.visited.set(cell.0, true);
+        self.current = Some(cell);
+        self.expanded += 1;
+    }
+}
+
+/// Progress through a maze, i.e. everything that decides where a route can go from here on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub cell: CellId,
+    /// Bitmask of the colours of the keys held.
+    pub keys: u32,
+    /// Number of checkpoints visited so far.
+    pub reached: usize,
+}
+
+impl Progress {
+    /// Progress at the start of the maze, with any keys lying there picked up.
+    pub fn start(maze: &Maze) -> Self {
+        Self {
+            cell: maze.start,
+            keys: maze.collect_keys(maze.start, 0),
+            reached: 0,
+        }
+    }
+
+    /// Progress of the walk through the maze so far.
+    pub fn current(maze: &Maze) -> Self {
+        Self {
+            cell: maze.position,
+            keys: maze.keys_held,
+            reached: maze.checkpoints_reached,
+        }
+    }
+}
+
+/// Checkpoint or end that a route has to get to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waypoint {
+    /// Checkpoint with the given index, counting from 0.
+    Checkpoint(usize),
+    End,
+}
+
+impl std::fmt::Display for Waypoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Waypoint::Checkpoint(index) => write!(f, "checkpoint {}", index + 1),
+            Waypoint::End => write!(f, "the end"),
+        }
+    }
+}
+
+/// Reason that there is no route on to the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoPath {
+    /// The waypoint cannot be reached from anywhere the route can get to after the waypoint before
+    /// it, not even with every key.
+    Unreachable(Waypoint),
+    /// The waypoint would be reachable with every key, but the keys needed cannot be picked up on
+    /// the way.
+    Locked(Waypoint),
+}
+
+impl NoPath {
+    /// Work out why no solver finds a route on from the given progress, which must be the case.
+    pub fn explain(maze: &Maze, from: Progress) -> NoPath {
+        let space = StateSpace::new(maze, from);
+        let visited = space.flood(maze, [space.start()]);
+
+        // The search gets stuck before the first waypoint that none of the states reached.
+        let reached = (0..space.len())
+            .filter(|state| visited[*state])
+            .map(|state| space.decode(state).2)
+            .max()
+            .unwrap();
+
+        let waypoint = match reached {
+            reached if reached < maze.checkpoints.len() => Waypoint::Checkpoint(reached),
+            _ => Waypoint::End,
+        };
+
+        let all_keys = (space.key_count - 1) as u32;
+        let unlocked = space.flood(
+            maze,
+            (0..space.len())
+                .filter(|state| visited[*state])
+                .map(|state| space.decode(state))
+                .filter(|(_, _, state_reached)| *state_reached == reached)
+                .map(|(cell, _, _)| space.encode(cell, all_keys, reached))
+        );
+
+        let target = space.waypoints[reached];
+        let arrived = (0..space.len())
+            .filter(|state| unlocked[*state])
+            .any(|state| space.decode(state).0 == target);
+
+        if arrived {
+            NoPath::Locked(waypoint)
+        } else {
+            NoPath::Unreachable(waypoint)
+        }
+    }
+}
+
+impl std::fmt::Display for NoPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoPath::Unreachable(waypoint) => write!(f, "{waypoint} cannot be reached"),
+            NoPath::Locked(waypoint) => write!(f, "{waypoint} is locked behind doors whose keys cannot be reached"),
+        }
+    }
+}
+
+impl std::error::Error for NoPath {}
+
+/// States of a search over a maze, numbered with the cell in the low digits, then the keys held,
+/// then the number of checkpoints reached.
+struct StateSpace {
+    cell_count: usize,
+    key_count: usize,
+    /// Waypoints still to be visited after reaching each number of checkpoints.
+    waypoints: Vec<CellId>,
+    /// Lower bound on the distance from each waypoint on to the end.
+    remaining: Vec<usize>,
+    start: usize,
+}
+
+impl StateSpace {
+    fn new(maze: &Maze, from: Progress) -> Self {
+        let mut waypoints = maze.checkpoints.clone();
+        waypoints.push(maze.end);
+
+        let mut remaining = vec![0; waypoints.len()];
+        for index in (0..waypoints.len() - 1).rev() {
+            remaining[index] = remaining[index + 1] + maze.portal_distance(waypoints[index], waypoints[index + 1]);
+        }
+
+        let mut result = Self {
+            cell_count: maze.cell_count(),
+            key_count: 1 << maze.keys.len(),
+            waypoints,
+            remaining,
+            start: 0,
+        };
+        result.start = result.encode(from.cell, from.keys, from.reached);
+        result
+    }
+
+    fn len(&self) -> usize {
+        self.cell_count * self.key_count * self.waypoints.len()
+    }
+
+    fn encode(&self, cell: CellId, keys: u32, reached: usize) -> usize {
+        (reached * self.key_count + keys as usize) * self.cell_count + cell.0
+    }
+
+    fn decode(&self, state: usize) -> (CellId, u32, usize) {
+        let cell = CellId(state % self.cell_count);
+        let keys = (state / self.cell_count % self.key_count) as u32;
+        let reached = state / self.cell_count / self.key_count;
+        (cell, keys, reached)
+    }
+
+    fn start(&self) -> usize {
+        self.start
+    }
+
+    fn is_goal(&self, state: usize) -> bool {
+        let (cell, _, reached) = self.decode(state);
+        reached == self.waypoints.len() - 1 && cell == self.waypoints[reached]
+    }
+
+    /// Every state at the end with every checkpoint reached, whatever the keys held.
+    fn goals(&self) -> impl Iterator<Item = usize> + '_ {
+        let reached = self.waypoints.len() - 1;
+        (0..self.key_count).map(move |keys| self.encode(self.waypoints[reached], keys as u32, reached))
+    }
+
+    /// Never overestimates the number of steps left to the end, and changes by at most one with
+    /// every step, which is what A* needs to be able to skip states it has already expanded.
+    fn heuristic(&self, maze: &Maze, state: usize) -> usize {
+        let (cell, _, reached) = self.decode(state);
+        maze.portal_distance(cell, self.waypoints[reached]) + self.remaining[reached]
+    }
+
+    /// Iterate over the states one step away, moving on through portals.
+    fn successors<'a>(&'a self, maze: &'a Maze, state: usize) -> impl Iterator<Item = usize> + 'a {
+        let (cell, keys, reached) = self.decode(state);
+        maze
+            .neighbours(cell)
+            .filter(move |(wall, _)| maze.can_pass(cell, wall, keys))
+            .map(move |(_, neighbour_cell)| {
+                let neighbour_cell = maze.portal(neighbour_cell).unwrap_or(neighbour_cell);
+                let neighbour_keys = maze.collect_keys(neighbour_cell, keys);
+                let neighbour_reached = reached + (maze.checkpoints.get(reached) == Some(&neighbour_cell)) as usize;
+                self.encode(neighbour_cell, neighbour_keys, neighbour_reached)
+            })
+    }
+
+    /// Get the states one step before, i.e. the states that have this state as a successor. Some of
+    /// them might never be reached from the start, e.g. ones missing the key lying in their cell.
+    fn predecessors(&self, maze: &Maze, state: usize) -> Vec<usize> {
+        let (cell, keys, reached) = self.decode(state);
+
+        // The cell is only ever landed on by stepping onto the other end of its portal.
+        let entry_cell = maze.portal(cell).unwrap_or(cell);
+
+        // Landing on a checkpoint that is next in line always counts it.
+        let mut previous_reached = Vec::new();
+        if maze.checkpoints.get(reached) != Some(&cell) {
+            previous_reached.push(reached);
+        }
+        if reached > 0 && maze.checkpoints[reached - 1] == cell {
+            previous_reached.push(reached - 1);
+        }
+
+        // Any of the keys lying in the cell might have just been picked up.
+        let picked_keys = maze.collect_keys(cell, 0) & keys;
+
+        let mut result = Vec::new();
+        for (wall, neighbour_cell) in maze.neighbours(entry_cell) {
+            let mut dropped_keys = picked_keys;
+            loop {
+                let previous_keys = keys & !dropped_keys;
+                if maze.can_pass(neighbour_cell, &wall, previous_keys) {
+                    for previous_reached in previous_reached.iter() {
+                        result.push(self.encode(neighbour_cell, previous_keys, *previous_reached));
+                    }
+                }
+
+                if dropped_keys == 0 {
+                    break;
+                }
+                dropped_keys = (dropped_keys - 1) & picked_keys;
+            }
+        }
+        result
+    }
+
+    /// Mark every state that can be reached from any of the given states.
+    fn flood(&self, maze: &Maze, states: impl IntoIterator<Item = usize>) -> Vec<bool> {
+        let mut visited = vec![false; self.len()];
+        let mut stack = Vec::new();
+        for state in states {
+            visited[state] = true;
+            stack.push(state);
+        }
+
+        while let Some(state) = stack.pop() {
+            for neighbour_state in self.successors(maze, state) {
+                if !visited[neighbour_state] {
+                    visited[neighbour_state] = true;
+                    stack.push(neighbour_state);
+                }
+            }
+        }
+        visited
+    }
+
+    /// Follow links back from a state to the start of the search. Only the links along the path
+    /// are ever read, so it does not matter what the rest start out as.
+    fn path(&self, links: &[usize], mut state: usize, origin: usize) -> Vec<CellId> {
+        let mut path = vec![self.decode(state).0];
+        while state != origin {
+            state = links[state];
+            path.push(self.decode(state).0);
+        }
+        path
+    }
+}
+
+/// Breadth first search from the start, which finds a shortest route since every step costs the
+/// same.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BreadthFirst;
+
+impl Solver for BreadthFirst {
+    fn start(&self, maze: &Maze, from: Progress) -> Box<dyn SolverStepper> {
+        let space = StateSpace::new(maze, from);
+        let start = space.start();
+
+        let mut visited = vec![false; space.len()];
+        let links = vec![start; space.len()];
+        let queue = VecDeque::from([start]);
+        visited[start] = true;
+
+        let trace = SearchTrace::new(maze.cell_count());
+        Box::new(BreadthFirstStepper { space, visited, links, queue, trace })
+    }
+}
+
+struct BreadthFirstStepper {
+    space: StateSpace,
+    visited: Vec<bool>,
+    links: Vec<usize>,
+    queue: VecDeque<usize>,
+    trace: SearchTrace,
+}
+
+impl SolverStepper for BreadthFirstStepper {
+    fn step(&mut self, maze: &Maze) -> SearchStep {
+        let Some(state) = self.queue.pop_front() else {
+            return SearchStep::Exhausted;
+        };
+
+        if self.space.is_goal(state) {
+            let mut path = self.space.path(&self.links, state, self.space.start());
+            path.reverse();
+            return SearchStep::Found(path);
+        }
+
+        self.trace.expand(self.space.decode(state).0);
+
+        for neighbour_state in self.space.successors(maze, state) {
+            if !self.visited[neighbour_state] {
+                self.visited[neighbour_state] = true;
+                self.links[neighbour_state] = state;
+                self.queue.push_back(neighbour_state);
+            }
+        }
+
+        SearchStep::Expanded
+    }
+
+    fn open(&self) -> Vec<CellId> {
+        self.queue.iter().map(|state| self.space.decode(*state).0).collect()
+    }
+
+    fn trace(&self) -> &SearchTrace {
+        &self.trace
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    g_score: usize,
+    f_score: usize,
+    state: usize,
+}
+
+impl BinaryHashHeapItem for Node {
+    type Key = usize;
+    type Value = usize;
+
+    fn key(&self) -> &Self::Key {
+        &self.state
+    }
+
+    fn value(&self) -> &Self::Value {
+        &self.f_score
+    }
+}
+
+/// Search expanding the state with the lowest cost so far plus heuristic first, using
+/// [BinaryHashHeap] so that the cost of a state on the heap can be lowered in place.
+struct BestFirstStepper {
+    space: StateSpace,
+    heuristic: fn(&StateSpace, &Maze, usize) -> usize,
+    open: BinaryHashHeap<Node>,
+    visited: Vec<bool>,
+    links: Vec<usize>,
+    trace: SearchTrace,
+}
+
+impl BestFirstStepper {
+    fn new(maze: &Maze, space: StateSpace, heuristic: fn(&StateSpace, &Maze, usize) -> usize) -> Self {
+        let start = space.start();
+
+        let mut open = BinaryHashHeap::default();
+        open.push(PushAction::Keep, Node {
+            state: start,
+            g_score: 0,
+            f_score: heuristic(&space, maze, start),
+        });
+
+        let visited = vec![false; space.len()];
+        let links = vec![start; space.len()];
+
+        let trace = SearchTrace::new(maze.cell_count());
+        Self { space, heuristic, open, visited, links, trace }
+    }
+}
+
+impl SolverStepper for BestFirstStepper {
+    fn step(&mut self, maze: &Maze) -> SearchStep {
+        let Some(node) = self.open.pop() else {
+            return SearchStep::Exhausted;
+        };
+
+        if self.space.is_goal(node.state) {
+            let mut path = self.space.path(&self.links, node.state, self.space.start());
+            path.reverse();
+            return SearchStep::Found(path);
+        }
+
+        self.trace.expand(self.space.decode(node.state).0);
+
+        // Marked before looking at the successors, since stepping onto a portal right next to the
+        // other end leads straight back to the same state.
+        self.visited[node.state] = true;
+
+        for state in self.space.successors(maze, node.state) {
+            if self.visited[state] {
+                continue;
+            }
+
+            let g_score = node.g_score + 1;
+            let f_score = g_score + (self.heuristic)(&self.space, maze, state);
+            if !self.open.push(PushAction::DecreaseKey, Node {
+                state,
+                g_score, f_score,
+            }) {
+                continue;
+            }
+
+            self.links[state] = node.state;
+        }
+
+        SearchStep::Expanded
+    }
+
+    fn open(&self) -> Vec<CellId> {
+        self.open.iter().map(|node| self.space.decode(node.state).0).collect()
+    }
+
+    fn trace(&self) -> &SearchTrace {
+        &self.trace
+    }
+}
+
+/// Dijkstra's algorithm, i.e. A* without a heuristic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dijkstra;
+
+impl Solver for Dijkstra {
+    fn start(&self, maze: &Maze, from: Progress) -> Box<dyn SolverStepper> {
+        Box::new(BestFirstStepper::new(maze, StateSpace::new(maze, from), |_, _, _| 0))
+    }
+}
+
+/// A* guided by the taxicab distance to the next waypoint, taking the shorter way around along
+/// axes that wrap, plus the distance between the remaining waypoints. Portals can only ever make
+/// the route shorter, so the heuristic also allows for going through whichever portal is closest.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AStar;
+
+impl Solver for AStar {
+    fn start(&self, maze: &Maze, from: Progress) -> Box<dyn SolverStepper> {
+        Box::new(BestFirstStepper::new(maze, StateSpace::new(maze, from), StateSpace::heuristic))
+    }
+}
+
+/// Breadth first search from the start and back from the end at the same time, one whole layer at
+/// a time from whichever side has the smaller frontier, until the two meet.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Bidirectional;
+
+impl Solver for Bidirectional {
+    fn start(&self, maze: &Maze, from: Progress) -> Box<dyn SolverStepper> {
+        let space = StateSpace::new(maze, from);
+        let start = space.start();
+
+        // Distance from the start or to the end of each state, and the next state towards it.
+        let mut distances = [vec![usize::MAX; space.len()], vec![usize::MAX; space.len()]];
+        let mut links = [vec![start; space.len()], vec![start; space.len()]];
+        let goals = space.goals().collect::<Vec<_>>();
+
+        distances[0][start] = 0;
+        for goal in goals.iter() {
+            distances[1][*goal] = 0;
+            links[1][*goal] = *goal;
+        }
+
+        // There is only the start on the forwards side, and at least one goal on the backwards
+        // side, so the first layer is always expanded forwards.
+        let trace = SearchTrace::new(maze.cell_count());
+        Box::new(BidirectionalStepper {
+            space,
+            distances,
+            links,
+            frontiers: [Vec::new(), goals],
+            side: 0,
+            layer: vec![start],
+            next_frontier: Vec::new(),
+            best: None,
+            trace,
+        })
+    }
+}
+
+struct BidirectionalStepper {
+    space: StateSpace,
+    distances: [Vec<usize>; 2],
+    links: [Vec<usize>; 2],
+    frontiers: [Vec<usize>; 2],
+    /// Side of the layer being expanded, 0 forwards from the start and 1 backwards from the end.
+    side: usize,
+    /// States of the layer being expanded that are still to be expanded.
+    layer: Vec<usize>,
+    next_frontier: Vec<usize>,
+    /// Length of the shortest route through a meeting found in the layer so far, and the state
+    /// they met at.
+    best: Option<(usize, usize)>,
+    trace: SearchTrace,
+}
+
+impl SolverStepper for BidirectionalStepper {
+    fn step(&mut self, maze: &Maze) -> SearchStep {
+        let start = self.space.start();
+        if self.space.is_goal(start) {
+            return SearchStep::Found(vec![self.space.decode(start).0]);
+        }
+
+        // Every meeting found while expanding a whole layer is considered, as the first one need
+        // not be on a shortest route.
+        if self.layer.is_empty() {
+            if let Some((_, meeting)) = self.best {
+                let mut path = self.space.path(&self.links[0], meeting, start);
+                path.reverse();
+
+                let mut state = meeting;
+                while !self.space.is_goal(state) {
+                    state = self.links[1][state];
+                    path.push(self.space.decode(state).0);
+                }
+                return SearchStep::Found(path);
+            }
+
+            self.frontiers[self.side] = std::mem::take(&mut self.next_frontier);
+            if self.frontiers[0].is_empty() || self.frontiers[1].is_empty() {
+                return SearchStep::Exhausted;
+            }
+
+            self.side = if self.frontiers[0].len() <= self.frontiers[1].len() { 0 } else { 1 };
+            self.layer = std::mem::take(&mut self.frontiers[self.side]);
+        }
+
+        let side = self.side;
+        let state = self.layer.pop().unwrap();
+        self.trace.expand(self.space.decode(state).0);
+
+        let neighbour_states = match side {
+            0 => self.space.successors(maze, state).collect::<Vec<_>>(),
+            _ => self.space.predecessors(maze, state),
+        };
+
+        for neighbour_state in neighbour_states {
+            if self.distances[side][neighbour_state] != usize::MAX {
+                continue;
+            }
+
+            self.distances[side][neighbour_state] = self.distances[side][state] + 1;
+            self.links[side][neighbour_state] = state;
+            self.next_frontier.push(neighbour_state);
+
+            let other_distance = self.distances[1 - side][neighbour_state];
+            if other_distance != usize::MAX {
+                let length = self.distances[side][neighbour_state] + other_distance;
+                if self.best.is_none_or(|(best_length, _)| length < best_length) {
+                    self.best = Some((length, neighbour_state));
+                }
+            }
+        }
+
+        SearchStep::Expanded
+    }
+
+    fn open(&self) -> Vec<CellId> {
+        self.layer
+            .iter()
+            .chain(self.next_frontier.iter())
+            .chain(self.frontiers.iter().flatten())
+            .map(|state| self.space.decode(*state).0)
+            .collect()
+    }
+
+    fn trace(&self) -> &SearchTrace {
+        &self.trace
+    }
+}
+
+/// Solvers selectable from the setup screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolverKind {
+    BreadthFirst,
+    Dijkstra,
+    AStar,
+    Bidirectional,
+}
+
+impl SolverKind {
+    pub const ALL: [SolverKind; 4] = [
+        SolverKind::BreadthFirst,
+        SolverKind::Dijkstra,
+        SolverKind::AStar,
+        SolverKind::Bidirectional,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SolverKind::BreadthFirst => "Breadth First",
+            SolverKind::Dijkstra => "Dijkstra",
+            SolverKind::AStar => "A*",
+            SolverKind::Bidirectional => "Bidirectional",
+        }
+    }
+
+    /// Cycle to the next solver, wrapping around if necessary.
+    pub fn next(self) -> SolverKind {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Cycle to the previous solver, wrapping around if necessary.
+    pub fn prev(self) -> SolverKind {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap();
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    pub fn solver(self) -> Box<dyn Solver> {
+        match self {
+            SolverKind::BreadthFirst => Box::new(BreadthFirst),
+            SolverKind::Dijkstra => Box::new(Dijkstra),
+            SolverKind::AStar => Box::new(AStar),
+            SolverKind::Bidirectional => Box::new(Bidirectional),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    use crate::Boundary;
+    use crate::generator::{GeneratorKind, SelectionPolicy};
+    use crate::test_support::check_route;
+
+    #[test]
+    fn test() {
+        let mut rng = StdRng::seed_from_u64(0xe5091b7c2d4a863f);
+        let boundaries = [
+            vec![Boundary::Wrap, Boundary::Wrap, Boundary::Wrap],
+            vec![Boundary::Bounded, Boundary::Twisted(0), Boundary::Wrap],
+            vec![Boundary::Twisted(2), Boundary::Bounded, Boundary::Twisted(1)],
+        ];
+
+        for _ in 0..64 {
+            let dimensions = (0..3).map(|_| rng.random_range(1..8)).collect::<Vec<_>>();
+            let mut maze = Maze::with_boundaries(dimensions, boundaries.choose(&mut rng).unwrap().clone());
+            let kind = *GeneratorKind::ALL.choose(&mut rng).unwrap();
+            maze.generate(kind.generator(SelectionPolicy::default(), rng.random_range(1..3)).as_ref(), &mut rng);
+            maze.braid(rng.random(), &mut rng);
+            maze.place_checkpoints(rng.random_range(0..3), &mut rng);
+            maze.place_doors(rng.random_range(0..3), &mut rng);
+            maze.make_one_way(rng.random_range(0.0..0.5), &mut rng);
+            maze.place_portals(rng.random_range(0..3), &mut rng);
+
+            let routes = SolverKind::ALL.map(|kind| kind.solver().solve(&maze, Progress::start(&maze)).unwrap());
+            for (kind, route) in std::iter::zip(SolverKind::ALL, routes.iter()) {
+                check_route(&maze, route);
+                assert_eq!(route.len(), routes[0].len(), "{}", kind.name());
+            }
+
+            // Solving on from part way along the route must agree with the rest of the route.
+            let index = rng.random_range(0..routes[0].len());
+            let mut progress = Progress::start(&maze);
+            for cell in routes[0][1..=index].iter() {
+                progress.cell = *cell;
+                progress.keys = maze.collect_keys(*cell, progress.keys);
+                progress.reached += (maze.checkpoints.get(progress.reached) == Some(cell)) as usize;
+            }
+
+            for kind in SolverKind::ALL {
+                let route = kind.solver().solve(&maze, progress).unwrap();
+                assert_eq!(route.first(), Some(&progress.cell));
+                assert_eq!(route.len(), routes[0].len() - index, "{}", kind.name());
+            }
+        }
+    }
+
+    #[test]
+    fn test_stepper() {
+        let mut rng = StdRng::seed_from_u64(0x29c4e7b05fd1a836);
+        let mut maze = Maze::new(vec![9, 8, 3]);
+        maze.generate(&crate::generator::Kruskal, &mut rng);
+        maze.braid(0.5, &mut rng);
+        maze.place_checkpoints(2, &mut rng);
+        maze.place_doors(2, &mut rng);
+
+        for kind in SolverKind::ALL {
+            let solver = kind.solver();
+            let mut stepper = solver.start(&maze, Progress::start(&maze));
+            assert!(stepper.trace().current.is_none());
+            assert!(stepper.open().contains(&maze.start));
+
+            let mut steps = 0;
+            let route = loop {
+                match stepper.step(&maze) {
+                    SearchStep::Expanded => steps += 1,
+                    SearchStep::Found(route) => break route,
+                    SearchStep::Exhausted => panic!("{} found no route", kind.name()),
+                }
+                assert!(stepper.trace().visited.get(stepper.trace().current.unwrap().0));
+            };
+
+            assert_eq!(stepper.trace().expanded, steps, "{}", kind.name());
+            assert!(stepper.trace().visited.get(maze.start.0));
+            assert_eq!(Some(route), solver.solve(&maze, Progress::start(&maze)));
+        }
+    }
+}
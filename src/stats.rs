@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::Maze;
+
+/// Summary of the structure of a maze, for comparing how hard mazes from different settings are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MazeStats {
+    /// Number of cells that are part of the maze.
+    pub cell_count: usize,
+    /// Number of cells with exactly one open wall.
+    pub dead_ends: usize,
+    /// Number of cells with three or more open walls, by number of open walls.
+    pub junctions: BTreeMap<usize, usize>,
+    /// Number of corridors, by length. A corridor is a maximal run of cells with exactly two open
+    /// walls.
+    pub corridors: BTreeMap<usize, usize>,
+    /// Number of steps in the solution, through every checkpoint, or [None] if the end cannot be
+    /// reached.
+    pub solution_length: Option<usize>,
+    /// Number of distinct cells on the solution.
+    pub solution_cells: usize,
+    /// Number of open walls along each axis.
+    pub open_walls: Vec<usize>,
+}
+
+impl MazeStats {
+    /// Analyse the maze. This solves the maze, so it is about as expensive as that.
+    pub fn new(maze: &Maze) -> Self {
+        let cells = maze.cells()
+            .filter(|cell| !maze.is_excluded(*cell))
+            .collect::<Vec<_>>();
+
+        let mut dead_ends = 0;
+        let mut junctions = BTreeMap::new();
+        for cell in cells.iter() {
+            match maze.degree(*cell) {
+                1 => dead_ends += 1,
+                degree @ 3.. => *junctions.entry(degree).or_insert(0) += 1,
+                _ => {},
+            }
+        }
+
+        // Flood fill over cells with two open walls, which can only ever extend a corridor in two
+        // directions.
+        let mut corridors = BTreeMap::new();
+        let mut visited = vec![false; maze.cell_count()];
+        for cell in cells.iter().copied() {
+            if maze.degree(cell) != 2 || visited[cell.0] {
+                continue;
+            }
+
+            visited[cell.0] = true;
+            let mut length = 0;
+            let mut stack = vec![cell];
+            while let Some(cell) = stack.pop() {
+                length += 1;
+                for (wall, neighbour_cell) in maze.neighbours(cell) {
+                    if !maze.get_wall(&wall) && maze.degree(neighbour_cell) == 2 && !visited[neighbour_cell.0] {
+                        visited[neighbour_cell.0] = true;
+                        stack.push(neighbour_cell);
+                    }
+                }
+            }
+            *corridors.entry(length).or_insert(0) += 1;
+        }
+
+        let mut open_walls = vec![0; maze.dimensions.len()];
+        for cell in cells.iter() {
+            for (axis, count) in open_walls.iter_mut().enumerate() {
+                if let Some((wall, _)) = maze.neighbour(*cell, axis, true) && !maze.get_wall(&wall) {
+                    *count += 1;
+                }
+            }
+        }
+
+        let solution = maze.solve().ok();
+        let solution_cells = solution.iter().flatten().collect::<HashSet<_>>().len();
+
+        Self {
+            cell_count: cells.len(),
+            dead_ends,
+            junctions,
+            corridors,
+            solution_length: solution.map(|solution| solution.len() - 1),
+            solution_cells,
+            open_walls,
+        }
+    }
+
+    /// Fraction of cells that lie on the solution.
+    pub fn solution_share(&self) -> f64 {
+        self.solution_cells as f64 / self.cell_count as f64
+    }
+
+    /// Average length of a corridor, which is higher for mazes with long winding passages and few
+    /// branches, or 0 if there are no corridors at all.
+    pub fn river_factor(&self) -> f64 {
+        let count = self.corridors.values().sum::<usize>();
+        if count == 0 {
+            return 0.0;
+        }
+
+        let cells = self.corridors.iter().map(|(length, count)| length * count).sum::<usize>();
+        cells as f64 / count as f64
+    }
+}
+
+impl fmt::Display for MazeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Cells: {}", self.cell_count)?;
+        writeln!(f, "Dead Ends: {} ({:.1}%)", self.dead_ends, self.dead_ends as f64 / self.cell_count as f64 * 100.0)?;
+
+        write!(f, "Junctions:")?;
+        if self.junctions.is_empty() {
+            write!(f, " none")?;
+        }
+        for (i, (degree, count)) in self.junctions.iter().enumerate() {
+            if i != 0 { write!(f, ",")?; }
+            write!(f, " {count} of degree {degree}")?;
+        }
+        writeln!(f)?;
+
+        write!(f, "Corridors:")?;
+        if self.corridors.is_empty() {
+            write!(f, " none")?;
+        }
+        for (i, (length, count)) in self.corridors.iter().enumerate() {
+            if i != 0 { write!(f, ",")?; }
+            write!(f, " {count} of length {length}")?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "River Factor: {:.2}", self.river_factor())?;
+        match self.solution_length {
+            Some(length) => writeln!(f, "Solution Length: {length}")?,
+            None => writeln!(f, "Solution Length: unreachable")?,
+        }
+        writeln!(f, "Solution Share: {:.1}%", self.solution_share() * 100.0)?;
+
+        write!(f, "Open Walls:")?;
+        for (axis, count) in self.open_walls.iter().enumerate() {
+            if axis != 0 { write!(f, ",")?; }
+            write!(f, " {count} along axis {axis}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    use crate::generator::Kruskal;
+
+    #[test]
+    fn test() {
+        let mut rng = StdRng::seed_from_u64(0x8a4d2c7e91f05b36);
+
+        let mut maze = Maze::new(vec![6, 7, 5]);
+        maze.generate(&Kruskal, &mut rng);
+
+        let stats = MazeStats::new(&maze);
+        assert_eq!(stats.cell_count, maze.cell_count());
+
+        // Every cell is a dead end, part of a corridor, or a junction, and a perfect maze is a tree.
+        let corridor_cells = stats.corridors.iter().map(|(length, count)| length * count).sum::<usize>();
+        let junction_cells = stats.junctions.values().sum::<usize>();
+        assert_eq!(stats.dead_ends + corridor_cells + junction_cells, stats.cell_count);
+        assert_eq!(stats.open_walls.iter().sum::<usize>(), stats.cell_count - 1);
+
+        let degrees = stats.dead_ends + corridor_cells * 2 + stats.junctions.iter().map(|(degree, count)| degree * count).sum::<usize>();
+        assert_eq!(degrees, stats.open_walls.iter().sum::<usize>() * 2);
+
+        // The solution of a perfect maze never visits a cell twice.
+        assert_eq!(Some(stats.solution_cells), stats.solution_length.map(|length| length + 1));
+
+        // Braiding only ever gets rid of dead ends.
+        maze.braid(1.0, &mut rng);
+        assert_eq!(MazeStats::new(&maze).dead_ends, 0);
+    }
+}
@@ -0,0 +1,31 @@
+use rand::prelude::*;
+
+use crate::{CellId, Maze};
+use crate::generator::Kruskal;
+
+/// Generate a maze with Kruskal's algorithm, then knock down the given fraction of its dead ends.
+pub fn braided_maze(dimensions: Vec<usize>, braid: f64, rng: &mut StdRng) -> Maze {
+    let mut maze = Maze::new(dimensions);
+    maze.generate(&Kruskal, rng);
+    maze.braid(braid, rng);
+    maze
+}
+
+/// Check that a route goes from the start through every checkpoint in order to the end, with
+/// every step going through a wall that can be passed with the keys picked up so far, possibly
+/// onto a portal that moves on to the other end.
+pub fn check_route(maze: &Maze, route: &[CellId]) {
+    assert_eq!(route.first(), Some(&maze.start));
+    assert_eq!(route.last(), Some(&maze.end));
+
+    let mut keys = maze.collect_keys(maze.start, 0);
+    let mut reached = 0;
+    for step in route.windows(2) {
+        assert!(maze.neighbours(step[0]).any(|(wall, cell)| maze.portal(cell).unwrap_or(cell) == step[1] && maze.can_pass(step[0], &wall, keys)));
+        keys = maze.collect_keys(step[1], keys);
+        if maze.checkpoints.get(reached) == Some(&step[1]) {
+            reached += 1;
+        }
+    }
+    assert_eq!(reached, maze.checkpoints.len());
+}